@@ -1,3 +1,33 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 fn main() {
+    // Build metadata reported by /api/version and `get_version`. Both can be set from the
+    // environment (e.g. in CI); otherwise they're read from git and the clock.
+    let git_hash = std::env::var("VIBECAST_GIT_HASH").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "--short", "HEAD"])
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .and_then(|out| String::from_utf8(out.stdout).ok())
+            .map(|hash| hash.trim().to_string())
+    });
+    if let Some(hash) = git_hash.filter(|h| !h.is_empty()) {
+        println!("cargo:rustc-env=VIBECAST_GIT_HASH={}", hash);
+    }
+
+    // Honour SOURCE_DATE_EPOCH for reproducible builds
+    let build_time = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs()));
+    if let Some(secs) = build_time {
+        println!("cargo:rustc-env=VIBECAST_BUILD_TIME={}", secs);
+    }
+    println!("cargo:rerun-if-env-changed=VIBECAST_GIT_HASH");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=../../../.git/HEAD");
+
     tauri_build::build()
 }
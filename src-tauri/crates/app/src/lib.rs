@@ -1,13 +1,41 @@
 use std::sync::Arc;
 use tauri::{Manager, Emitter};
-use local_ip_address::local_ip;
+use local_ip_address::{list_afinet_netifas, local_ip};
 use vibe_cast_audio::AudioState;
 use vibe_cast_state::AppStateSync;
 use vibe_cast_models::{
     MessageConfig, VisualizationPreset, TextStylePreset, 
-    CommonSettings, flatten_message_tree_value
+    CommonSettings, VersionInfo, WindowGeometry, WindowLayout, WindowPlacement, TRIGGER_TARGET_ALL, flatten_message_tree_value, is_valid_trigger_target
 };
 
+/// Non-loopback IPv4 addresses of this machine, most likely LAN address first: private
+/// ranges (home networks before VPN-style 10.x), then other addresses. Ties keep the
+/// address `local_ip()` picked first. IPv6 is left out since the remote URL uses a bare host.
+fn lan_ip_candidates() -> Vec<std::net::Ipv4Addr> {
+    fn rank(ip: &std::net::Ipv4Addr) -> u8 {
+        match ip.octets() {
+            [192, 168, ..] => 0,
+            [172, b, ..] if (16..32).contains(&b) => 1,
+            [10, ..] => 2,
+            _ if ip.is_link_local() => 4,
+            _ => 3,
+        }
+    }
+    let preferred = local_ip().ok();
+    let mut ips: Vec<std::net::Ipv4Addr> = list_afinet_netifas()
+        .map(|ifs| ifs.into_iter()
+            .filter_map(|(_, ip)| match ip {
+                std::net::IpAddr::V4(v4) => Some(v4),
+                std::net::IpAddr::V6(_) => None,
+            })
+            .collect())
+        .unwrap_or_default();
+    ips.retain(|ip| !ip.is_loopback() && !ip.is_unspecified());
+    ips.sort_by_key(|ip| (rank(ip), Some(std::net::IpAddr::V4(*ip)) != preferred));
+    ips.dedup();
+    ips
+}
+
 #[tauri::command]
 async fn get_server_info(state: tauri::State<'_, Arc<AppStateSync>>) -> Result<serde_json::Value, String> {
     let start = std::time::Instant::now();
@@ -17,11 +45,24 @@ async fn get_server_info(state: tauri::State<'_, Arc<AppStateSync>>) -> Result<s
     loop {
         if let Ok(port_lock) = state.server_port.lock() {
             if *port_lock != 0 {
-                let my_local_ip = local_ip().map(|ip| ip.to_string()).unwrap_or_else(|_| "127.0.0.1".to_string());
-                return Ok(serde_json::json!({
-                    "ip": my_local_ip,
-                    "port": *port_lock
-                }));
+                let candidates = lan_ip_candidates();
+                let advertised = state.advertised_ip.lock().ok().and_then(|ip| *ip);
+                let ip = advertised
+                    .map(|ip| ip.to_string())
+                    .or_else(|| candidates.first().map(|ip| ip.to_string()))
+                    .unwrap_or_else(|| "127.0.0.1".to_string());
+                let mut info = serde_json::json!({
+                    "ip": ip,
+                    "port": *port_lock,
+                    "candidates": candidates.iter().map(|ip| ip.to_string()).collect::<Vec<_>>(),
+                    "advertised": advertised.is_some(),
+                });
+                if advertised.is_none() && candidates.is_empty() {
+                    info["warning"] = serde_json::json!(
+                        "No network address found; only this computer can reach the server (check Wi-Fi/Ethernet)"
+                    );
+                }
+                return Ok(info);
             }
         }
         
@@ -33,12 +74,129 @@ async fn get_server_info(state: tauri::State<'_, Arc<AppStateSync>>) -> Result<s
     }
 }
 
+/// Pin the address advertised in the remote URL/QR code; an empty string goes back to auto-detection
+#[tauri::command]
+fn set_advertised_ip(state: tauri::State<'_, Arc<AppStateSync>>, ip: String) -> Result<(), String> {
+    let ip = ip.trim();
+    let parsed = if ip.is_empty() {
+        None
+    } else {
+        match ip.parse::<std::net::IpAddr>() {
+            // The remote URL is built as http://<ip>:<port>, which needs brackets for IPv6
+            Ok(std::net::IpAddr::V6(_)) => return Err("Only IPv4 addresses can be advertised".to_string()),
+            Ok(addr) => Some(addr),
+            Err(_) => return Err(format!("Invalid IP address: {}", ip)),
+        }
+    };
+    if let Ok(mut advertised) = state.advertised_ip.lock() {
+        *advertised = parsed;
+    }
+    Ok(())
+}
+
+/// Version of this build; git hash and build time come from build.rs
+fn version_info() -> VersionInfo {
+    VersionInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: option_env!("VIBECAST_GIT_HASH").map(|s| s.to_string()),
+        build_time: option_env!("VIBECAST_BUILD_TIME").and_then(|s| s.parse().ok()),
+        tauri_version: tauri::VERSION.to_string(),
+    }
+}
+
+#[tauri::command]
+fn get_version() -> VersionInfo {
+    version_info()
+}
+
 #[tauri::command]
 fn get_audio_data(state: tauri::State<'_, AudioState>) -> Vec<f32> {
-    match state.fft_data.lock() {
-        Ok(data) => data.clone(),
-        Err(_) => vec![],
+    state.current_fft()
+}
+
+/// The current spectrum grouped into `band_count` logarithmic bands
+#[tauri::command]
+fn get_audio_bands(state: tauri::State<'_, AudioState>, band_count: usize) -> Vec<f32> {
+    vibe_cast_audio::aggregate_bands(&state.current_fft(), band_count)
+}
+
+#[tauri::command]
+fn freeze_audio(state: tauri::State<'_, AudioState>) {
+    state.freeze();
+}
+
+#[tauri::command]
+fn unfreeze_audio(state: tauri::State<'_, AudioState>) {
+    state.unfreeze();
+}
+
+/// Input peak/RMS (0..1) and whether it is clipping, for a setup level meter
+#[tauri::command]
+fn get_audio_levels(state: tauri::State<'_, AudioState>) -> serde_json::Value {
+    let levels = state.levels();
+    serde_json::json!({
+        "peak": levels.peak,
+        "rms": levels.rms,
+        "clipping": levels.clipping,
+    })
+}
+
+#[tauri::command]
+fn list_audio_devices() -> Result<Vec<String>, String> {
+    vibe_cast_audio::list_input_devices()
+}
+
+/// Capture from another input device; returns the device name now in use
+#[tauri::command]
+async fn set_audio_device(handle: tauri::AppHandle, name: String) -> Result<String, String> {
+    // Switching blocks until the new stream runs, so keep it off the main thread
+    tauri::async_runtime::spawn_blocking(move || handle.state::<AudioState>().set_device(&name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Injecting synthetic audio is only allowed when `VIBECAST_TEST_AUDIO` is set
+fn test_audio_enabled() -> bool {
+    std::env::var("VIBECAST_TEST_AUDIO")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Replace the live spectrum with `data` (for demos and E2E tests of reactive visuals)
+/// until `clear_test_audio_data` is called
+#[tauri::command]
+fn set_test_audio_data(
+    handle: tauri::AppHandle,
+    state: tauri::State<'_, AudioState>,
+    data: Vec<f32>,
+) -> Result<(), String> {
+    if !test_audio_enabled() {
+        return Err("Test audio is disabled (set VIBECAST_TEST_AUDIO=1)".to_string());
+    }
+    if data.iter().any(|v| !v.is_finite()) {
+        return Err("Audio data must be finite numbers".to_string());
     }
+    state.set_test_data(data.clone());
+    // Capture may not be producing frames (e.g. no input device), so push it out directly
+    let _ = handle.emit("audio-data", data);
+    Ok(())
+}
+
+#[tauri::command]
+fn clear_test_audio_data(state: tauri::State<'_, AudioState>) {
+    state.clear_test_data();
+}
+
+/// Set the `audio-data` event rate (Hz, clamped to 1..=240); returns the applied rate
+#[tauri::command]
+fn set_audio_emit_rate(state: tauri::State<'_, AudioState>, rate_hz: f64) -> u32 {
+    state.set_target_fps(rate_hz)
+}
+
+/// Set how many new samples are collected between FFTs (clamped to 1..=1024); returns the applied value
+#[tauri::command]
+fn set_audio_hop_size(state: tauri::State<'_, AudioState>, hop_size: usize) -> usize {
+    state.set_hop_size(hop_size)
 }
 
 /// Helper function to resolve paths relative to config base path
@@ -95,6 +253,22 @@ fn get_config_base_path(
     }
 }
 
+/// Write the current state to `path` in the config schema, so it can be loaded on the next launch.
+/// Relative paths are resolved against the config base path.
+#[tauri::command]
+fn save_config(
+    state: tauri::State<'_, Arc<AppStateSync>>,
+    path: String
+) -> Result<(), String> {
+    let base_path_opt = state.config_base_path.lock()
+        .ok()
+        .and_then(|p| p.clone());
+    let resolved = resolve_path(&path, base_path_opt.as_deref());
+    state.save_config_to_file(&resolved)?;
+    eprintln!("[Rust] Saved config to: {}", resolved);
+    Ok(())
+}
+
 #[tauri::command]
 fn load_message_text_file(
     state: tauri::State<'_, Arc<AppStateSync>>,
@@ -124,8 +298,26 @@ fn load_message_text_file(
     }
 }
 
+/// Server-only mode (`--headless` or `VIBECAST_HEADLESS`): the config windows are closed at
+/// startup and never recreated, while audio capture and the LAN server keep running.
+/// Closing the last window doesn't quit the app; the remote `shutdown` command (or a signal) does.
+struct HeadlessMode(bool);
+
+/// Message stats file from `VIBECAST_STATS_FILE`, saved on exit
+struct StatsFile(String);
+
+fn headless_requested(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--headless")
+        || std::env::var("VIBECAST_HEADLESS")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false)
+}
+
 #[tauri::command]
-fn restart_viz_window(handle: tauri::AppHandle) -> Result<(), String> {
+fn restart_viz_window(handle: tauri::AppHandle, headless: tauri::State<'_, HeadlessMode>) -> Result<(), String> {
+    if headless.0 {
+        return Err("Running headless; windows are disabled".to_string());
+    }
     // Close existing viz window (if any)
     let mut prev_pos: Option<tauri::PhysicalPosition<i32>> = None;
     let mut prev_size: Option<tauri::PhysicalSize<u32>> = None;
@@ -156,6 +348,184 @@ fn restart_viz_window(handle: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// Enter or leave fullscreen on the viz window, restoring its windowed size/position on exit
+#[tauri::command]
+fn set_viz_fullscreen(
+    handle: tauri::AppHandle,
+    state: tauri::State<'_, Arc<AppStateSync>>,
+    fullscreen: bool,
+) -> Result<(), String> {
+    vibe_cast_server::set_viz_fullscreen(&handle, &state, Some(fullscreen)).map(|_| ())
+}
+
+/// PNG of the viz window's current contents, also written to `path` when given
+#[tauri::command]
+async fn capture_viz_screenshot(
+    handle: tauri::AppHandle,
+    path: Option<String>,
+) -> Result<tauri::ipc::Response, String> {
+    let bytes = tauri::async_runtime::spawn_blocking(move || vibe_cast_server::capture_viz_screenshot(&handle))
+        .await
+        .map_err(|e| e.to_string())??;
+    if let Some(path) = path.filter(|p| !p.is_empty()) {
+        std::fs::write(&path, &bytes).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(tauri::ipc::Response::new(bytes))
+}
+
+fn monitor_info(index: usize, monitor: &tauri::Monitor) -> serde_json::Value {
+    let position = monitor.position();
+    let size = monitor.size();
+    serde_json::json!({
+        "index": index,
+        "name": monitor.name(),
+        "x": position.x,
+        "y": position.y,
+        "width": size.width,
+        "height": size.height,
+        "scaleFactor": monitor.scale_factor()
+    })
+}
+
+/// List available monitors; the `index` is what `move_viz_window` takes
+#[tauri::command]
+fn list_monitors(handle: tauri::AppHandle) -> Result<Vec<serde_json::Value>, String> {
+    let monitors = handle.available_monitors().map_err(|e| e.to_string())?;
+    Ok(monitors.iter().enumerate().map(|(i, m)| monitor_info(i, m)).collect())
+}
+
+/// Place the viz window on a monitor (by `list_monitors` index), optionally fullscreen
+#[tauri::command]
+fn move_viz_window(handle: tauri::AppHandle, monitor_index: usize, fullscreen: bool) -> Result<(), String> {
+    let window = handle.get_webview_window("viz").ok_or("Viz window not found")?;
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+    let monitor = monitors.get(monitor_index).ok_or_else(|| {
+        format!("Monitor index {} out of range ({} monitors)", monitor_index, monitors.len())
+    })?;
+
+    // A fullscreen window has to leave fullscreen before it can move to another monitor
+    if window.is_fullscreen().unwrap_or(false) {
+        window.set_fullscreen(false).map_err(|e| e.to_string())?;
+    }
+    window.set_position(*monitor.position()).map_err(|e| e.to_string())?;
+    if fullscreen {
+        window.set_fullscreen(true).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Saved window layouts, by name, in the app data dir
+const WINDOW_LAYOUTS_FILE: &str = "window-layouts.json";
+
+/// Windows captured by `save_window_layout`
+const LAYOUT_WINDOWS: &[&str] = &["main", "viz"];
+
+fn window_layouts_path(handle: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    let dir = handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir.join(WINDOW_LAYOUTS_FILE))
+}
+
+fn read_window_layouts(path: &std::path::Path) -> Result<std::collections::BTreeMap<String, WindowLayout>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Default::default()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Save the main and viz windows' positions, sizes and monitors under `name`
+#[tauri::command]
+fn save_window_layout(
+    handle: tauri::AppHandle,
+    state: tauri::State<'_, Arc<AppStateSync>>,
+    name: String,
+) -> Result<(), String> {
+    let mut layout = WindowLayout::default();
+    for label in LAYOUT_WINDOWS {
+        let Some(window) = handle.get_webview_window(label) else { continue };
+        let mut position = window.outer_position().map_err(|e| e.to_string())?;
+        let mut size = window.inner_size().map_err(|e| e.to_string())?;
+        // A fullscreen viz window is saved with its windowed geometry, to return to on restore
+        if *label == "viz" {
+            if let Some(g) = state.viz_windowed_geometry.lock().ok().and_then(|g| *g) {
+                position = tauri::PhysicalPosition::new(g.x, g.y);
+                size = tauri::PhysicalSize::new(g.width, g.height);
+            }
+        }
+        let monitor = window.current_monitor().ok().flatten();
+        let origin = monitor.as_ref().map(|m| *m.position()).unwrap_or(tauri::PhysicalPosition::new(0, 0));
+        layout.windows.insert(label.to_string(), WindowPlacement {
+            geometry: WindowGeometry {
+                x: position.x - origin.x,
+                y: position.y - origin.y,
+                width: size.width,
+                height: size.height,
+            },
+            monitor: monitor.as_ref().and_then(|m| m.name().cloned()),
+            fullscreen: window.is_fullscreen().unwrap_or(false),
+        });
+    }
+
+    let path = window_layouts_path(&handle)?;
+    let mut layouts = read_window_layouts(&path)?;
+    layouts.insert(name, layout);
+    let content = serde_json::to_string_pretty(&layouts).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Apply a layout saved with `save_window_layout`. Windows whose monitor is no longer
+/// connected go to the primary monitor instead.
+#[tauri::command]
+fn restore_window_layout(
+    handle: tauri::AppHandle,
+    state: tauri::State<'_, Arc<AppStateSync>>,
+    name: String,
+) -> Result<(), String> {
+    let layouts = read_window_layouts(&window_layouts_path(&handle)?)?;
+    let layout = layouts.get(&name).ok_or_else(|| format!("No window layout named '{}'", name))?;
+    let monitors = handle.available_monitors().map_err(|e| e.to_string())?;
+    let primary = handle.primary_monitor().ok().flatten();
+
+    for (label, placement) in &layout.windows {
+        let Some(window) = handle.get_webview_window(label) else { continue };
+        let saved_monitor = placement.monitor.as_ref()
+            .and_then(|name| monitors.iter().find(|m| m.name() == Some(name)));
+        let g = placement.geometry;
+        let (origin, offset) = match (saved_monitor, primary.as_ref()) {
+            (Some(m), _) => (*m.position(), (g.x, g.y)),
+            (None, Some(p)) => {
+                // Keep the window on the fallback monitor even if it is smaller
+                let bounds = p.size();
+                let max_x = bounds.width.saturating_sub(g.width) as i32;
+                let max_y = bounds.height.saturating_sub(g.height) as i32;
+                (*p.position(), (g.x.clamp(0, max_x), g.y.clamp(0, max_y)))
+            }
+            (None, None) => (tauri::PhysicalPosition::new(0, 0), (g.x, g.y)),
+        };
+
+        // Leave fullscreen before moving; the viz window goes through the shared helper so
+        // its remembered windowed geometry stays consistent
+        if *label == "viz" {
+            vibe_cast_server::set_viz_fullscreen(&handle, &state, Some(false))?;
+        } else if window.is_fullscreen().unwrap_or(false) {
+            window.set_fullscreen(false).map_err(|e| e.to_string())?;
+        }
+        window.set_size(tauri::PhysicalSize::new(g.width, g.height)).map_err(|e| e.to_string())?;
+        window.set_position(tauri::PhysicalPosition::new(origin.x + offset.0, origin.y + offset.1))
+            .map_err(|e| e.to_string())?;
+        if placement.fullscreen {
+            if *label == "viz" {
+                vibe_cast_server::set_viz_fullscreen(&handle, &state, Some(true))?;
+            } else {
+                window.set_fullscreen(true).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
 fn emit_state_change(
     handle: tauri::AppHandle, 
@@ -217,12 +587,27 @@ fn emit_state_change(
             }
         }
         "RESET_MESSAGE_STATS" => {
+            let message_id = payload_value.get("messageId").and_then(|v| v.as_str());
             if let Ok(mut m) = state.message_stats.lock() {
-                *m = serde_json::json!({});
+                match message_id {
+                    Some(id) => {
+                        if let Some(obj) = m.as_object_mut() {
+                            obj.remove(id);
+                        }
+                    }
+                    None => *m = serde_json::json!({}),
+                }
             }
         }
         "TRIGGER_MESSAGE" => {
             if let Ok(msg) = serde_json::from_value::<MessageConfig>(payload_value.clone()) {
+                let target = payload_value.get("target")
+                    .and_then(|v| v.as_str())
+                    .filter(|t| is_valid_trigger_target(t))
+                    .unwrap_or(TRIGGER_TARGET_ALL);
+                state.set_triggered_target(target);
+                state.set_active_message(Some(&msg.id));
+                state.record_recent_trigger(&msg.id);
                 triggered_message = Some(msg);
             }
         }
@@ -475,6 +860,8 @@ fn list_images_in_folder(
                 }
             }
             media_files.sort();
+            // The slideshow fetches these through /api/images/serve
+            state.allow_media_root(path);
             eprintln!("Found {} media files in folder", media_files.len());
             if media_files.is_empty() {
                 eprintln!("WARNING: No media files found in folder");
@@ -492,17 +879,39 @@ fn list_images_in_folder(
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Keep recent stdout/stderr in memory for GET /api/logs
+    vibe_cast_server::capture_process_output();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             get_server_info,
+            set_advertised_ip,
+            get_version,
             get_audio_data,
+            freeze_audio,
+            unfreeze_audio,
+            set_audio_emit_rate,
+            set_audio_hop_size,
+            get_audio_levels,
+            get_audio_bands,
+            list_audio_devices,
+            set_audio_device,
+            set_test_audio_data,
+            clear_test_audio_data,
+            list_monitors,
+            move_viz_window,
+            set_viz_fullscreen,
+            capture_viz_screenshot,
+            save_window_layout,
+            restore_window_layout,
             restart_viz_window,
             emit_state_change,
             set_config_base_path,
             get_config_base_path,
+            save_config,
             load_message_text_file,
             list_images_in_folder
         ])
@@ -511,6 +920,22 @@ pub fn run() {
             
             // Create shared app state for syncing
             let app_state_sync = Arc::new(AppStateSync::new());
+            if let Ok(mut v) = app_state_sync.version_info.lock() {
+                *v = Some(version_info());
+            }
+            
+            // Opt-in: mirror the event/access logs to a JSONL file, reloading the last
+            // VIBECAST_LOG_RELOAD entries so the logs continue across restarts
+            if let Ok(log_path) = std::env::var("VIBECAST_LOG_FILE") {
+                let reload = std::env::var("VIBECAST_LOG_RELOAD")
+                    .ok()
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(0);
+                match app_state_sync.enable_log_file(&log_path, reload) {
+                    Ok(()) => eprintln!("Logging to {} (reloaded up to {} entries)", log_path, reload),
+                    Err(e) => eprintln!("Warning: {}", e),
+                }
+            }
             
             // Parse command-line arguments for config file
             // Note: We use --app-config to avoid conflict with Tauri's --config flag
@@ -525,6 +950,12 @@ pub fn run() {
                 }
             }
             
+            let headless = headless_requested(&args);
+            if headless {
+                eprintln!("Headless mode: running audio and the LAN server without windows");
+            }
+            app.manage(HeadlessMode(headless));
+            
             let mut config_path: Option<String> = None;
             
             for i in 0..args.len() {
@@ -551,12 +982,31 @@ pub fn run() {
                 }
             }
             
+            // Strict mode fails the load on any malformed section instead of skipping it
+            let strict_config = std::env::var("VIBECAST_CONFIG_STRICT")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false);
+            
+            // Opt-in: write live edits back to the launch config file
+            let autosave_config = std::env::var("VIBECAST_CONFIG_AUTOSAVE")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false);
+            
             // Load config if provided
             if let Some(path) = config_path {
-                eprintln!("Attempting to load config from: {}", path);
-                match app_state_sync.load_config_from_file(&path) {
+                // Recorded even if loading fails, so a fixed file can be reloaded without a restart
+                if let Ok(mut launch) = app_state_sync.launch_config.lock() {
+                    *launch = Some((path.clone(), strict_config));
+                }
+                eprintln!("Attempting to load config from: {} (strict: {})", path, strict_config);
+                match app_state_sync.load_config_from_file(&path, strict_config) {
                     Ok(_) => {
                         eprintln!("Successfully loaded config from: {}", path);
+                        // Only after a successful load, so a bad file isn't overwritten with defaults
+                        if autosave_config {
+                            eprintln!("Config autosave enabled for: {}", path);
+                            app_state_sync.spawn_config_autosave(path.clone());
+                        }
                     }
                     Err(e) => {
                         eprintln!("Warning: Failed to load config from {}: {}", path, e);
@@ -566,9 +1016,26 @@ pub fn run() {
                 eprintln!("No config file specified (use --app-config <path> or set VIBECAST_CONFIG env var)");
             }
             
+            // Opt-in: keep a snapshot of the live state at a fixed path, independent of the launch config
+            if let Some(path) = std::env::var("VIBECAST_AUTOSAVE").ok().filter(|p| !p.is_empty()) {
+                eprintln!("State autosave enabled for: {}", path);
+                app_state_sync.spawn_config_autosave(path);
+            }
+            
+            // Opt-in: persist message stats to their own file (gzipped if it ends in .gz),
+            // loaded now and written back on exit
+            if let Some(path) = std::env::var("VIBECAST_STATS_FILE").ok().filter(|p| !p.is_empty()) {
+                if std::path::Path::new(&path).exists() {
+                    if let Err(e) = app_state_sync.load_stats_from_file(&path) {
+                        eprintln!("Warning: Failed to load message stats from {}: {}", path, e);
+                    }
+                }
+                app.manage(StatsFile(path));
+            }
+            
             app.manage(app_state_sync.clone());
             
-            // Start audio capture and manage the state to keep the stream alive
+            // Start audio capture; the managed state is also the handle used to stop it on exit
             let audio_state = vibe_cast_audio::start_audio_capture(handle);
             app.manage(audio_state);
 
@@ -579,11 +1046,40 @@ pub fn run() {
                 vibe_cast_server::start_server(handle, server_state, 8080).await;
             });
 
-            // Ensure we have the windows
-            let _main_window = app.get_webview_window("main").unwrap();
+            if headless {
+                // The windows from the config are created before setup runs; drop them
+                for window in app.webview_windows().into_values() {
+                    let _ = window.close();
+                }
+            } else if app.get_webview_window("main").is_none() {
+                eprintln!("Warning: main window not found");
+            }
             
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| match event {
+            // Headless has no windows, so "last window closed" must not end the server
+            tauri::RunEvent::ExitRequested { code: None, api, .. }
+                if app_handle.try_state::<HeadlessMode>().is_some_and(|h| h.0) =>
+            {
+                api.prevent_exit();
+            }
+            tauri::RunEvent::Exit => {
+                // Release the audio device instead of leaving it to process teardown
+                if let Some(audio_state) = app_handle.try_state::<AudioState>() {
+                    audio_state.shutdown();
+                }
+                if let (Some(stats_file), Some(sync)) = (
+                    app_handle.try_state::<StatsFile>(),
+                    app_handle.try_state::<Arc<AppStateSync>>(),
+                ) {
+                    if let Err(e) = sync.save_stats_to_file(&stats_file.0) {
+                        eprintln!("Warning: Failed to save message stats to {}: {}", stats_file.0, e);
+                    }
+                }
+            }
+            _ => {}
+        });
 }
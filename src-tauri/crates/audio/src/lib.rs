@@ -1,85 +1,606 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use realfft::RealFftPlanner;
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
+const FFT_SIZE: usize = 1024;
+
+/// Clamp a requested hop size to 1..=FFT_SIZE
+fn clamp_hop_size(hop_size: usize) -> usize {
+    hop_size.clamp(1, FFT_SIZE)
+}
+
+/// Append `data` to the sliding analysis `buffer`, calling `on_window` with each full
+/// `window`-sample window, then sliding forward by `hop` samples (a full hop means no overlap)
+fn push_samples(buffer: &mut Vec<f32>, data: &[f32], window: usize, hop: usize, mut on_window: impl FnMut(&[f32])) {
+    for &sample in data {
+        buffer.push(sample);
+        if buffer.len() >= window {
+            on_window(&buffer[..window]);
+            buffer.drain(..hop.min(buffer.len()));
+        }
+    }
+}
+
+/// Samples at or beyond this magnitude count as clipping
+const CLIP_THRESHOLD: f32 = 0.99;
+/// How long a clipped sample keeps the clipping flag raised, so a meter can show it
+const CLIP_HOLD: Duration = Duration::from_secs(1);
+/// Per-callback falloff of the level meter: the peak decays and the RMS is smoothed by this factor
+const LEVEL_DECAY: f32 = 0.9;
+
+/// Peak magnitude and RMS of a block of samples
+pub fn block_levels(samples: &[f32]) -> (f32, f32) {
+    if samples.is_empty() {
+        return (0.0, 0.0);
+    }
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    let mean_square = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    (peak, mean_square.sqrt())
+}
+
+/// Bin boundaries for `band_count` logarithmic bands over bins `1..bin_count` (DC skipped).
+/// Band `i` covers `edges[i]..edges[i + 1]`; every band gets at least one bin, so the band
+/// count is capped by the number of bins available.
+fn log_band_edges(bin_count: usize, band_count: usize) -> Vec<usize> {
+    if bin_count < 2 || band_count == 0 {
+        return vec![];
+    }
+    let (lo, hi) = (1usize, bin_count);
+    let bands = band_count.min(hi - lo);
+    let ratio = hi as f64 / lo as f64;
+    let mut edges = Vec::with_capacity(bands + 1);
+    edges.push(lo);
+    for i in 1..=bands {
+        let target = (lo as f64 * ratio.powf(i as f64 / bands as f64)).round() as usize;
+        let prev = edges[i - 1];
+        // At least one bin per band, while leaving one for each band still to come
+        edges.push(target.max(prev + 1).min(hi - (bands - i)));
+    }
+    edges
+}
+
+/// Group linear FFT magnitudes into `band_count` logarithmic bands (narrow bass, wide treble).
+/// Each band is the mean magnitude of its bins, so wide bands aren't favoured over narrow ones.
+pub fn aggregate_bands(magnitudes: &[f32], band_count: usize) -> Vec<f32> {
+    let edges = log_band_edges(magnitudes.len(), band_count);
+    edges
+        .windows(2)
+        .map(|w| {
+            let bins = &magnitudes[w[0]..w[1]];
+            bins.iter().sum::<f32>() / bins.len() as f32
+        })
+        .collect()
+}
+
+/// Input levels for a setup meter (0..1 of full scale)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InputLevels {
+    pub peak: f32,
+    pub rms: f32,
+    /// A sample reached full scale within the last `CLIP_HOLD`
+    pub clipping: bool,
+}
+
+/// Rolling peak/RMS over recent capture callbacks
+#[derive(Default)]
+struct LevelMeter {
+    peak: f32,
+    mean_square: f32,
+    last_clip: Option<Instant>,
+}
+
+impl LevelMeter {
+    fn update(&mut self, samples: &[f32]) {
+        let (peak, rms) = block_levels(samples);
+        self.peak = peak.max(self.peak * LEVEL_DECAY);
+        self.mean_square = self.mean_square * LEVEL_DECAY + rms * rms * (1.0 - LEVEL_DECAY);
+        if peak >= CLIP_THRESHOLD {
+            self.last_clip = Some(Instant::now());
+        }
+    }
+
+    fn levels(&self) -> InputLevels {
+        InputLevels {
+            peak: self.peak,
+            rms: self.mean_square.sqrt(),
+            clipping: self.last_clip.is_some_and(|t| t.elapsed() < CLIP_HOLD),
+        }
+    }
+}
+
+/// Default rate of `audio-data` events to the webview
+pub const DEFAULT_TARGET_FPS: u32 = 60;
+const MIN_TARGET_FPS: u32 = 1;
+const MAX_TARGET_FPS: u32 = 240;
+/// Without a new FFT frame for this long (e.g. no input device), the emitter goes quiet
+const STALE_FRAME_AFTER: Duration = Duration::from_millis(500);
+
 pub struct AudioState {
     pub fft_data: Arc<Mutex<Vec<f32>>>,
+    /// Spectrum held for a freeze-frame effect; emitted and returned instead of live data while set
+    pub frozen_fft: Arc<Mutex<Option<Vec<f32>>>>,
+    /// Rate at which the emitter sends the latest spectrum as `audio-data`
+    pub target_fps: Arc<AtomicU32>,
+    /// New samples collected between FFTs; below `FFT_SIZE` the analysis windows overlap
+    pub hop_size: Arc<AtomicUsize>,
+    /// While set, `fft_data` holds injected test data and capture doesn't overwrite it
+    pub test_data_active: Arc<AtomicBool>,
+    level_meter: Arc<Mutex<LevelMeter>>,
+    /// Name of the input device being captured, once a stream is running
+    device_name: Arc<Mutex<Option<String>>>,
+    /// Device switches and shutdown for the capture thread
+    control_tx: Mutex<Option<mpsc::Sender<AudioControl>>>,
+    capture_thread: Mutex<Option<JoinHandle<()>>>,
+    emitter_running: Arc<AtomicBool>,
+    emitter_thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Requests to the capture thread, which owns the (non-`Send`) cpal stream
+enum AudioControl {
+    /// Capture from the named input device; replies with the device name or why it failed
+    SetDevice(String, mpsc::Sender<Result<String, String>>),
+    /// Drop the stream and release the device
+    Shutdown,
+}
+
+/// Shared state the capture callback writes into, handed to every stream that is built
+#[derive(Clone)]
+struct CaptureTargets {
+    app_handle: AppHandle,
+    fft_data: Arc<Mutex<Vec<f32>>>,
+    hop_size: Arc<AtomicUsize>,
+    test_data_active: Arc<AtomicBool>,
+    level_meter: Arc<Mutex<LevelMeter>>,
+    /// Bumped on every FFT frame, so the emitter can tell when capture has stalled
+    frame_seq: Arc<AtomicU64>,
+}
+
+impl AudioState {
+    /// Hold the current spectrum until `unfreeze` is called
+    pub fn freeze(&self) {
+        let snapshot = self.fft_data.lock().map(|d| d.clone()).unwrap_or_default();
+        if let Ok(mut frozen) = self.frozen_fft.lock() {
+            *frozen = Some(snapshot);
+        }
+    }
+
+    pub fn unfreeze(&self) {
+        if let Ok(mut frozen) = self.frozen_fft.lock() {
+            *frozen = None;
+        }
+    }
+
+    /// Current input levels, to verify audio is actually arriving
+    pub fn levels(&self) -> InputLevels {
+        self.level_meter.lock().map(|m| m.levels()).unwrap_or_default()
+    }
+
+    /// Replace the live spectrum with synthetic `data` until `clear_test_data` is called
+    pub fn set_test_data(&self, data: Vec<f32>) {
+        if let Ok(mut shared) = self.fft_data.lock() {
+            *shared = data;
+        }
+        self.test_data_active.store(true, Ordering::Relaxed);
+    }
+
+    /// Let live capture update the spectrum again
+    pub fn clear_test_data(&self) {
+        self.test_data_active.store(false, Ordering::Relaxed);
+    }
+
+    /// Emit `audio-data` at `fps` events per second (clamped to 1..=240); returns the applied rate.
+    /// `fft_data` is still updated on every FFT frame.
+    pub fn set_target_fps(&self, fps: f64) -> u32 {
+        let fps = if fps.is_finite() {
+            (fps.round() as u32).clamp(MIN_TARGET_FPS, MAX_TARGET_FPS)
+        } else {
+            DEFAULT_TARGET_FPS
+        };
+        self.target_fps.store(fps, Ordering::Relaxed);
+        fps
+    }
+
+    /// Recompute the FFT every `hop_size` new samples (clamped to 1..=FFT_SIZE).
+    /// Smaller hops give smoother, lower-latency updates at the cost of more FFTs.
+    pub fn set_hop_size(&self, hop_size: usize) -> usize {
+        let hop_size = clamp_hop_size(hop_size);
+        self.hop_size.store(hop_size, Ordering::Relaxed);
+        hop_size
+    }
+
+    /// Name of the input device being captured (`None` if no device could be opened)
+    pub fn device_name(&self) -> Option<String> {
+        self.device_name.lock().ok().and_then(|d| d.clone())
+    }
+
+    /// Switch capture to the input device called `name`. Blocks until the new stream is
+    /// running; if it can't be opened, the previous device keeps capturing.
+    pub fn set_device(&self, name: &str) -> Result<String, String> {
+        let not_running = || "Audio capture is not running".to_string();
+        let tx = self.control_tx.lock().ok().and_then(|t| t.clone()).ok_or_else(not_running)?;
+        let (reply_tx, reply_rx) = mpsc::channel();
+        tx.send(AudioControl::SetDevice(name.to_string(), reply_tx)).map_err(|_| not_running())?;
+        reply_rx.recv().map_err(|_| not_running())?
+    }
+
+    /// Stop capturing and release the audio device. Blocks until the capture and emitter
+    /// threads exit; calling it again is a no-op.
+    pub fn shutdown(&self) {
+        self.emitter_running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.emitter_thread.lock().ok().and_then(|mut h| h.take()) {
+            let _ = handle.join();
+        }
+        if let Some(tx) = self.control_tx.lock().ok().and_then(|mut t| t.take()) {
+            let _ = tx.send(AudioControl::Shutdown);
+        }
+        if let Some(handle) = self.capture_thread.lock().ok().and_then(|mut h| h.take()) {
+            let _ = handle.join();
+            println!("Audio capture stopped");
+        }
+    }
+
+    /// The spectrum consumers should see: the frozen snapshot if any, otherwise live data
+    pub fn current_fft(&self) -> Vec<f32> {
+        if let Some(frozen) = self.frozen_fft.lock().ok().and_then(|f| f.clone()) {
+            return frozen;
+        }
+        self.fft_data.lock().map(|d| d.clone()).unwrap_or_default()
+    }
+}
+
+/// Input device names, for picking the capture device
+pub fn list_input_devices() -> Result<Vec<String>, String> {
+    let devices = cpal::default_host()
+        .input_devices()
+        .map_err(|e| format!("Failed to get input devices: {}", e))?;
+    Ok(devices.filter_map(|d| d.name().ok()).collect())
 }
 
 pub fn start_audio_capture(app_handle: AppHandle) -> AudioState {
+    let fft_data = Arc::new(Mutex::new(vec![0.0; FFT_SIZE / 2]));
+    let frozen_fft = Arc::new(Mutex::new(None::<Vec<f32>>));
+    let target_fps = Arc::new(AtomicU32::new(DEFAULT_TARGET_FPS));
+    // Non-overlapping windows by default
+    let hop_size = Arc::new(AtomicUsize::new(FFT_SIZE));
+    let test_data_active = Arc::new(AtomicBool::new(false));
+    let level_meter = Arc::new(Mutex::new(LevelMeter::default()));
+    let device_name = Arc::new(Mutex::new(None::<String>));
+    let frame_seq = Arc::new(AtomicU64::new(0));
+
+    // The callback only updates `fft_data`; a separate thread emits it at a steady rate,
+    // independent of buffer sizes and sample rate
+    let emitter_running = Arc::new(AtomicBool::new(true));
+    let emitter = SpectrumEmitter {
+        app_handle: app_handle.clone(),
+        fft_data: fft_data.clone(),
+        frozen_fft: frozen_fft.clone(),
+        test_data_active: test_data_active.clone(),
+        frame_seq: frame_seq.clone(),
+        target_fps: target_fps.clone(),
+        running: emitter_running.clone(),
+    };
+    let emitter_thread = std::thread::Builder::new()
+        .name("audio-emitter".to_string())
+        .spawn(move || emitter.run())
+        .expect("Failed to spawn audio emitter thread");
+
+    // cpal::Stream is not Send, so a dedicated thread owns the stream for its whole lifetime,
+    // rebuilding it on device changes and dropping it (releasing the device) on shutdown.
+    let (control_tx, control_rx) = mpsc::channel::<AudioControl>();
+    let (ready_tx, ready_rx) = mpsc::channel::<()>();
+    let targets = CaptureTargets {
+        app_handle,
+        fft_data: fft_data.clone(),
+        hop_size: hop_size.clone(),
+        test_data_active: test_data_active.clone(),
+        level_meter: level_meter.clone(),
+        frame_seq: frame_seq.clone(),
+    };
+    let thread_device_name = device_name.clone();
+    let capture_thread = std::thread::Builder::new()
+        .name("audio-capture".to_string())
+        .spawn(move || {
+            let set_device_name = |name: &str| {
+                if let Ok(mut d) = thread_device_name.lock() {
+                    *d = Some(name.to_string());
+                }
+            };
+            // Without a usable device, keep running so one can be selected later
+            let mut stream = match build_capture_stream(&targets, None) {
+                Ok((stream, name)) => {
+                    set_device_name(&name);
+                    Some(stream)
+                }
+                Err(e) => {
+                    eprintln!("Audio capture unavailable: {}", e);
+                    None
+                }
+            };
+            let _ = ready_tx.send(());
+            // Runs until shutdown is requested or the AudioState is dropped
+            while let Ok(control) = control_rx.recv() {
+                match control {
+                    AudioControl::SetDevice(name, reply) => {
+                        // Build the new stream first, so a failed switch keeps the old device
+                        let result = build_capture_stream(&targets, Some(&name)).map(|(new_stream, name)| {
+                            stream = Some(new_stream);
+                            set_device_name(&name);
+                            name
+                        });
+                        let _ = reply.send(result);
+                    }
+                    AudioControl::Shutdown => break,
+                }
+            }
+            drop(stream);
+        })
+        .expect("Failed to spawn audio capture thread");
+    ready_rx.recv().expect("Audio capture failed to start");
+
+    AudioState {
+        fft_data,
+        frozen_fft,
+        target_fps,
+        hop_size,
+        test_data_active,
+        level_meter,
+        device_name,
+        control_tx: Mutex::new(Some(control_tx)),
+        capture_thread: Mutex::new(Some(capture_thread)),
+        emitter_running,
+        emitter_thread: Mutex::new(Some(emitter_thread)),
+    }
+}
+
+/// Sends the latest spectrum as `audio-data` at `target_fps`
+struct SpectrumEmitter {
+    app_handle: AppHandle,
+    fft_data: Arc<Mutex<Vec<f32>>>,
+    frozen_fft: Arc<Mutex<Option<Vec<f32>>>>,
+    test_data_active: Arc<AtomicBool>,
+    frame_seq: Arc<AtomicU64>,
+    target_fps: Arc<AtomicU32>,
+    running: Arc<AtomicBool>,
+}
+
+impl SpectrumEmitter {
+    /// Emit until `running` is cleared. Live data is only sent while capture keeps producing
+    /// frames; a frozen or injected spectrum is sent regardless.
+    fn run(self) {
+        let mut next = Instant::now();
+        let mut last_seq = self.frame_seq.load(Ordering::Relaxed);
+        let mut last_frame_at: Option<Instant> = None;
+        while self.running.load(Ordering::Relaxed) {
+            let fps = self.target_fps.load(Ordering::Relaxed).clamp(MIN_TARGET_FPS, MAX_TARGET_FPS);
+            next += Duration::from_secs_f64(1.0 / fps as f64);
+            let now = Instant::now();
+            if next > now {
+                std::thread::sleep(next - now);
+            } else {
+                // Fell behind (e.g. the machine slept): resume from now rather than bursting
+                next = now;
+            }
+
+            let seq = self.frame_seq.load(Ordering::Relaxed);
+            if seq != last_seq {
+                last_seq = seq;
+                last_frame_at = Some(Instant::now());
+            }
+            let frozen = self.frozen_fft.lock().ok().and_then(|f| f.clone());
+            let live = self.test_data_active.load(Ordering::Relaxed)
+                || last_frame_at.is_some_and(|t| t.elapsed() < STALE_FRAME_AFTER);
+            let spectrum = match frozen {
+                Some(frozen) => frozen,
+                None if live => self.fft_data.lock().map(|d| d.clone()).unwrap_or_default(),
+                None => continue,
+            };
+            let _ = self.app_handle.emit("audio-data", spectrum);
+        }
+    }
+}
+
+/// Build and start a capture stream on the input device called `device_name`, or on the
+/// preferred loopback/default device when `None`. Returns the stream and the device's name.
+fn build_capture_stream(
+    targets: &CaptureTargets,
+    device_name: Option<&str>,
+) -> Result<(cpal::Stream, String), String> {
     let host = cpal::default_host();
-    
-    // On macOS, loopback usually requires a virtual device like BlackHole.
-    // We'll try to find a device with "BlackHole" in the name, otherwise use default input.
-    let device = host
+    let mut devices = host
         .input_devices()
-        .expect("Failed to get input devices")
-        .find(|d| d.name().map(|n| n.contains("BlackHole")).unwrap_or(false))
-        .or_else(|| host.default_input_device())
-        .expect("No input device found");
+        .map_err(|e| format!("Failed to get input devices: {}", e))?;
+    let device = match device_name {
+        Some(name) => devices
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+            .ok_or_else(|| format!("Audio device not found: {}", name))?,
+        // On macOS, loopback usually requires a virtual device like BlackHole.
+        // We'll try to find a device with "BlackHole" in the name, otherwise use default input.
+        None => devices
+            .find(|d| d.name().map(|n| n.contains("BlackHole")).unwrap_or(false))
+            .or_else(|| host.default_input_device())
+            .ok_or("No input device found")?,
+    };
+    let name = device.name().unwrap_or_default();
 
-    println!("Using audio device: {}", device.name().unwrap_or_default());
+    println!("Using audio device: {}", name);
 
     let config: cpal::StreamConfig = device
         .default_input_config()
-        .expect("Failed to get default input config")
+        .map_err(|e| format!("Failed to get default input config: {}", e))?
         .into();
 
     let _sample_rate = config.sample_rate.0 as usize;
-    let fft_size = 1024;
+    let fft_size = FFT_SIZE;
     let mut planner = RealFftPlanner::<f32>::new();
     let fft = planner.plan_fft_forward(fft_size);
 
     let mut buffer = Vec::with_capacity(fft_size);
-    let app_handle_clone = app_handle.clone();
-    
-    let fft_data = Arc::new(Mutex::new(vec![0.0; fft_size / 2]));
-    let fft_data_clone = fft_data.clone();
+    let CaptureTargets { app_handle: error_handle, fft_data, hop_size, test_data_active, level_meter, frame_seq } =
+        targets.clone();
+    let error_device = name.clone();
 
     let stream = device.build_input_stream(
         &config,
         move |data: &[f32], _: &cpal::InputCallbackInfo| {
-            for &sample in data {
-                buffer.push(sample);
-                if buffer.len() >= fft_size {
-                    // Process FFT
-                    let mut indata = buffer.clone();
-                    let mut outdata = fft.make_output_vec();
-                    if fft.process(&mut indata, &mut outdata).is_ok() {
-                        // Calculate magnitudes and normalize
-                        let magnitudes: Vec<f32> = outdata
-                            .iter()
-                            .take(fft_size / 2)
-                            .map(|c| (c.re * c.re + c.im * c.im).sqrt() / (fft_size as f32).sqrt())
-                            .collect();
-
-                        // Update shared state
-                        if let Ok(mut shared) = fft_data_clone.lock() {
-                            *shared = magnitudes.clone();
-                        }
+            if let Ok(mut meter) = level_meter.lock() {
+                meter.update(data);
+            }
+            let hop = clamp_hop_size(hop_size.load(Ordering::Relaxed));
+            push_samples(&mut buffer, data, fft_size, hop, |window| {
+                // Process FFT
+                let mut indata = window.to_vec();
+                let mut outdata = fft.make_output_vec();
+                if fft.process(&mut indata, &mut outdata).is_ok() {
+                    // Calculate magnitudes and normalize
+                    let magnitudes: Vec<f32> = outdata
+                        .iter()
+                        .take(fft_size / 2)
+                        .map(|c| (c.re * c.re + c.im * c.im).sqrt() / (fft_size as f32).sqrt())
+                        .collect();
 
-                        // Emit to the frontend
-                        let _ = app_handle_clone.emit("audio-data", magnitudes);
+                    // Update shared state (emitted by the emitter thread), unless
+                    // injected test data is holding it
+                    if !test_data_active.load(Ordering::Relaxed) {
+                        if let Ok(mut shared) = fft_data.lock() {
+                            *shared = magnitudes;
+                        }
                     }
-                    buffer.clear();
+                    frame_seq.fetch_add(1, Ordering::Relaxed);
                 }
+            });
+        },
+        move |err| {
+            eprintln!("Audio stream error: {}", err);
+            // e.g. an interface unplugged mid-show, so the UI can offer another device
+            if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                let _ = error_handle.emit("audio-error", HashMap::from([
+                    ("error", "device-not-available".to_string()),
+                    ("device", error_device.clone()),
+                    ("message", err.to_string()),
+                ]));
             }
         },
-        |err| eprintln!("Audio stream error: {}", err),
         None,
-    ).expect("Failed to build input stream");
+    ).map_err(|e| format!("Failed to build input stream: {}", e))?;
 
-    stream.play().expect("Failed to play audio stream");
-    
-    // Keep the stream alive for the app's lifetime.
-    // Note: cpal::Stream is not Send+Sync, so we can't store it in Tauri state.
-    // Using mem::forget is the standard workaround for long-running audio streams.
-    std::mem::forget(stream);
+    stream.play().map_err(|e| format!("Failed to play audio stream: {}", e))?;
+    Ok((stream, name))
+}
 
-    AudioState {
-        fft_data,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn band_edges_are_logarithmic_and_cover_every_bin() {
+        assert_eq!(log_band_edges(512, 8), vec![1, 2, 5, 10, 23, 49, 108, 235, 512]);
+        let magnitudes: Vec<f32> = (0..512).map(|i| i as f32).collect();
+        assert_eq!(
+            aggregate_bands(&magnitudes, 8),
+            vec![1.0, 3.0, 7.0, 16.0, 35.5, 78.0, 171.0, 373.0]
+        );
     }
-}
 
+    #[test]
+    fn band_count_is_capped_by_available_bins() {
+        assert_eq!(log_band_edges(6, 10), vec![1, 2, 3, 4, 5, 6]);
+        let magnitudes = [9.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(aggregate_bands(&magnitudes, 10), vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn empty_input_gives_no_bands() {
+        assert!(aggregate_bands(&[], 8).is_empty());
+        assert!(aggregate_bands(&[1.0], 8).is_empty());
+        assert!(aggregate_bands(&[1.0, 2.0], 0).is_empty());
+    }
+
+    #[test]
+    fn block_levels_of_dc_and_sine() {
+        assert_eq!(block_levels(&[0.5; 64]), (0.5, 0.5));
+        assert_eq!(block_levels(&[]), (0.0, 0.0));
+
+        let sine: Vec<f32> = (0..1024).map(|i| (i as f32 / 1024.0 * std::f32::consts::TAU * 4.0).sin()).collect();
+        let (peak, rms) = block_levels(&sine);
+        assert!((peak - 1.0).abs() < 1e-3, "peak {peak}");
+        assert!((rms - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-3, "rms {rms}");
+    }
+
+    #[test]
+    fn level_meter_holds_peaks_and_flags_clipping() {
+        let mut meter = LevelMeter::default();
+        meter.update(&[1.0, -0.2]);
+        let levels = meter.levels();
+        assert_eq!(levels.peak, 1.0);
+        assert!(levels.clipping);
+
+        meter.update(&[0.1; 8]);
+        let levels = meter.levels();
+        assert!((levels.peak - LEVEL_DECAY).abs() < 1e-6, "peak decays: {}", levels.peak);
+        assert!(levels.rms > 0.0 && levels.rms < 1.0);
+    }
+
+    #[test]
+    fn sliding_buffer_emits_a_window_every_hop() {
+        let samples: Vec<f32> = (0..16).map(|i| i as f32).collect();
+        let mut buffer = Vec::new();
+        let mut windows = Vec::new();
+        push_samples(&mut buffer, &samples[..6], 8, 2, |w| windows.push(w.to_vec()));
+        assert!(windows.is_empty());
+        push_samples(&mut buffer, &samples[6..], 8, 2, |w| windows.push(w.to_vec()));
+        let starts: Vec<f32> = windows.iter().map(|w| w[0]).collect();
+        assert_eq!(starts, vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+        assert!(windows.iter().all(|w| w.len() == 8));
+        // The overlap stays buffered for the next callback
+        assert_eq!(buffer, (10..16).map(|i| i as f32).collect::<Vec<_>>());
+
+        let mut buffer = Vec::new();
+        let mut count = 0;
+        push_samples(&mut buffer, &samples, 8, 8, |_| count += 1);
+        assert_eq!((count, buffer.len()), (2, 0));
+    }
 
+    /// An `AudioState` with no capture or emitter thread behind it
+    fn idle_state() -> AudioState {
+        AudioState {
+            fft_data: Arc::new(Mutex::new(vec![0.0; FFT_SIZE / 2])),
+            frozen_fft: Arc::new(Mutex::new(None)),
+            target_fps: Arc::new(AtomicU32::new(DEFAULT_TARGET_FPS)),
+            hop_size: Arc::new(AtomicUsize::new(FFT_SIZE)),
+            test_data_active: Arc::new(AtomicBool::new(false)),
+            level_meter: Arc::new(Mutex::new(LevelMeter::default())),
+            device_name: Arc::new(Mutex::new(None)),
+            control_tx: Mutex::new(None),
+            capture_thread: Mutex::new(None),
+            emitter_running: Arc::new(AtomicBool::new(false)),
+            emitter_thread: Mutex::new(None),
+        }
+    }
+
+    #[test]
+    fn frozen_spectrum_is_read_until_unfrozen() {
+        let state = idle_state();
+        *state.fft_data.lock().unwrap() = vec![0.25, 0.5];
+        state.freeze();
+        // Live capture keeps writing while frozen
+        *state.fft_data.lock().unwrap() = vec![0.9, 0.1];
+        assert_eq!(state.current_fft(), vec![0.25, 0.5]);
+        state.unfreeze();
+        assert_eq!(state.current_fft(), vec![0.9, 0.1]);
+    }
+
+    #[test]
+    fn injected_test_data_is_returned_as_the_spectrum() {
+        let state = idle_state();
+        state.set_test_data(vec![1.0, 0.5, 0.0]);
+        assert!(state.test_data_active.load(Ordering::Relaxed));
+        assert_eq!(state.current_fft(), vec![1.0, 0.5, 0.0]);
+        state.clear_test_data();
+        assert!(!state.test_data_active.load(Ordering::Relaxed));
+    }
+}
@@ -8,6 +8,8 @@ pub struct MessageConfig {
     pub text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text_file: Option<String>,
+    /// Empty when the message inherits its folder's default text style
+    #[serde(default)]
     pub text_style: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text_style_preset: Option<String>,
@@ -21,6 +23,89 @@ pub struct MessageConfig {
     pub split_enabled: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub split_separator: Option<String>,
+    /// Forced background color (`#rgb` / `#rrggbb` / `#rrggbbaa`), overriding the visualization
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    /// Opacity (0..1) of the background color overlay
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay_opacity: Option<f64>,
+    /// Visualization to switch to while this message plays; the previous one is restored afterwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visualization_override: Option<String>,
+}
+
+impl MessageConfig {
+    /// Validate the display override fields (`background_color`, `overlay_opacity`,
+    /// `visualization_override`)
+    pub fn validate_display_overrides(&self) -> Result<(), String> {
+        if let Some(color) = &self.background_color {
+            if !is_hex_color(color) {
+                return Err(format!("Message {}: invalid backgroundColor '{}'", self.id, color));
+            }
+        }
+        if let Some(opacity) = self.overlay_opacity {
+            if !(0.0..=1.0).contains(&opacity) {
+                return Err(format!("Message {}: overlayOpacity {} is outside 0..1", self.id, opacity));
+            }
+        }
+        if let Some(viz) = &self.visualization_override {
+            if !is_known_visualization(viz) {
+                return Err(format!("Message {}: unknown visualizationOverride '{}'", self.id, viz));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Visualization plugin provided by the frontend, as listed for remote controllers
+#[derive(Clone, Serialize, Debug)]
+pub struct VisualizationInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+}
+
+/// Visualizations in the frontend registry, in registry order
+pub const KNOWN_VISUALIZATIONS: &[VisualizationInfo] = &[
+    VisualizationInfo { id: "fireplace", name: "Fireplace" },
+    VisualizationInfo { id: "techno", name: "Techno" },
+    VisualizationInfo { id: "waves", name: "Waves" },
+    VisualizationInfo { id: "particles", name: "Particles" },
+    VisualizationInfo { id: "mushrooms", name: "Mushrooms" },
+    VisualizationInfo { id: "photo-slideshow", name: "Photo Slideshow" },
+    VisualizationInfo { id: "youtube", name: "YouTube" },
+    VisualizationInfo { id: "transition-demo", name: "Transition Demo" },
+];
+
+pub fn is_known_visualization(id: &str) -> bool {
+    KNOWN_VISUALIZATIONS.iter().any(|v| v.id == id)
+}
+
+/// Text style plugin provided by the frontend, as listed for style pickers
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStyleInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Honors `splitEnabled`/`splitSeparator` (credits uses split parts as lines)
+    pub supports_split: bool,
+    /// Has duration settings that a message's `speed` multiplier scales
+    pub supports_speed: bool,
+}
+
+/// Text styles in the frontend registry, in registry order
+pub const KNOWN_TEXT_STYLES: &[TextStyleInfo] = &[
+    TextStyleInfo { id: "scrolling-capitals", name: "Scrolling Capitals", supports_split: true, supports_speed: false },
+    TextStyleInfo { id: "fade", name: "Fade", supports_split: true, supports_speed: true },
+    TextStyleInfo { id: "typewriter", name: "Typewriter", supports_split: true, supports_speed: true },
+    TextStyleInfo { id: "bounce", name: "Bounce", supports_split: true, supports_speed: true },
+    TextStyleInfo { id: "dot-matrix", name: "Dot Matrix", supports_split: true, supports_speed: true },
+    TextStyleInfo { id: "credits", name: "Credits", supports_split: true, supports_speed: false },
+];
+
+/// `#rgb`, `#rrggbb` or `#rrggbbaa`
+pub fn is_hex_color(value: &str) -> bool {
+    value.strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()))
 }
 
 /// Visualization preset matching the frontend VisualizationPreset type
@@ -49,6 +134,66 @@ pub struct TextStylePreset {
     pub settings: serde_json::Value,
 }
 
+/// Global color theme applied across visualization and text style presets
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Theme {
+    #[serde(default)]
+    pub accent: Option<String>,
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// Preset setting keys recolored by a theme, and which theme color they take
+const THEME_ACCENT_KEYS: &[&str] = &["glowColor", "particleColor", "waveColor", "sphereColor", "cursorColor", "dotColor"];
+const THEME_BACKGROUND_KEYS: &[&str] = &["bgColor"];
+const THEME_TEXT_KEYS: &[&str] = &["color"];
+
+impl Theme {
+    pub fn validate(&self) -> Result<(), String> {
+        let colors = [("accent", &self.accent), ("background", &self.background), ("text", &self.text)];
+        if colors.iter().all(|(_, c)| c.is_none()) {
+            return Err("Theme needs at least one of accent, background, text".to_string());
+        }
+        for (name, color) in colors {
+            if let Some(color) = color {
+                if !is_hex_color(color) {
+                    return Err(format!("Invalid {} color '{}'", name, color));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the color settings a preset already has with the theme colors.
+    /// Returns the number of settings changed.
+    pub fn apply_to_settings(&self, settings: &mut serde_json::Value) -> usize {
+        let Some(obj) = settings.as_object_mut() else {
+            return 0;
+        };
+        let roles = [
+            (THEME_ACCENT_KEYS, &self.accent),
+            (THEME_BACKGROUND_KEYS, &self.background),
+            (THEME_TEXT_KEYS, &self.text),
+        ];
+        let mut changed = 0;
+        for (keys, color) in roles {
+            let Some(color) = color else { continue };
+            for key in keys {
+                if let Some(value) = obj.get_mut(*key) {
+                    if value.as_str() != Some(color.as_str()) {
+                        *value = serde_json::Value::String(color.clone());
+                        changed += 1;
+                    }
+                }
+            }
+        }
+        changed
+    }
+}
+
 /// Message statistics matching the frontend MessageStats type
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -67,9 +212,75 @@ pub struct TriggerHistory {
 
 /// Common visualization settings
 #[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct CommonSettings {
     pub intensity: f64,
     pub dim: f64,
+    /// When false, visualizations ignore audio but keep their idle animation
+    #[serde(default = "default_audio_reactive")]
+    pub audio_reactive: bool,
+    /// Aspect ratio of the output display (e.g. "16:9"), so visualizations can letterbox
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_aspect: Option<String>,
+    /// Baseline animation speed multiplier used while audio is silent or reactivity is off
+    #[serde(default = "default_idle_speed")]
+    pub idle_speed: f64,
+}
+
+fn default_audio_reactive() -> bool {
+    true
+}
+
+fn default_idle_speed() -> f64 {
+    1.0
+}
+
+/// Bounds for `CommonSettings::idle_speed`
+pub const IDLE_SPEED_MIN: f64 = 0.0;
+pub const IDLE_SPEED_MAX: f64 = 5.0;
+
+/// Key in `text_style_settings` holding the `TextAudioCoupling`
+pub const TEXT_AUDIO_COUPLING_KEY: &str = "audioCoupling";
+
+/// Frequency bands text glow can follow
+pub const TEXT_AUDIO_BANDS: &[&str] = &["low", "mid", "high"];
+
+/// How strongly message text glow pulses with the energy in a frequency band
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TextAudioCoupling {
+    pub enabled: bool,
+    /// One of `TEXT_AUDIO_BANDS`
+    pub band: String,
+    /// 0 (no modulation) to 1 (full glow swing)
+    pub amount: f64,
+}
+
+impl Default for TextAudioCoupling {
+    fn default() -> Self {
+        TextAudioCoupling { enabled: false, band: "low".to_string(), amount: 0.5 }
+    }
+}
+
+impl TextAudioCoupling {
+    pub fn validate(&self) -> Result<(), String> {
+        if !TEXT_AUDIO_BANDS.contains(&self.band.as_str()) {
+            return Err(format!("Unknown band '{}' (expected one of {})", self.band, TEXT_AUDIO_BANDS.join(", ")));
+        }
+        if !(0.0..=1.0).contains(&self.amount) {
+            return Err(format!("amount {} is outside 0..1", self.amount));
+        }
+        Ok(())
+    }
+}
+
+/// An aspect ratio hint is `W:H` with positive numbers, e.g. "16:9", "4:3" or "2.39:1"
+pub fn is_valid_aspect(aspect: &str) -> bool {
+    let Some((w, h)) = aspect.split_once(':') else {
+        return false;
+    };
+    let positive = |s: &str| s.trim().parse::<f64>().is_ok_and(|v| v.is_finite() && v > 0.0);
+    positive(w) && positive(h)
 }
 
 impl Default for CommonSettings {
@@ -77,6 +288,28 @@ impl Default for CommonSettings {
         Self {
             intensity: 1.0,
             dim: 1.0,
+            audio_reactive: default_audio_reactive(),
+            output_aspect: None,
+            idle_speed: default_idle_speed(),
+        }
+    }
+}
+
+/// Idle auto-dim configuration
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoDimSettings {
+    /// Dim after this many milliseconds without commands; `None` disables auto-dim
+    pub after_ms: Option<u64>,
+    /// Dim level to fade down to
+    pub floor: f64,
+}
+
+impl Default for AutoDimSettings {
+    fn default() -> Self {
+        Self {
+            after_ms: None,
+            floor: 0.2,
         }
     }
 }
@@ -88,6 +321,94 @@ pub struct FolderPlaybackQueue {
     pub folder_id: String,
     pub message_ids: Vec<String>,
     pub current_index: usize,
+    /// Preview playback loops and never records message stats
+    #[serde(default)]
+    pub preview: bool,
+    /// Speed multiplier applied to messages as they are triggered from the queue
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_multiplier: Option<f64>,
+    /// While paused, completing the current message does not advance the queue
+    #[serde(default)]
+    pub paused: bool,
+    /// Play the messages in random order, reshuffled each time a looping queue wraps around
+    #[serde(default)]
+    pub shuffle: bool,
+    /// Start over instead of finishing after the last message
+    #[serde(default)]
+    pub loop_playback: bool,
+}
+
+/// What a folder queue does when a message finishes playing
+#[derive(Debug, PartialEq)]
+pub enum QueueAdvance {
+    /// The finished message isn't the queue's current one
+    Ignored,
+    /// The queue is paused and stays on the current message
+    Held,
+    /// Play this message next
+    Next(String),
+    /// The last message finished; the queue should be dropped
+    Finished,
+}
+
+/// Bounds for `FolderPlaybackQueue::speed_multiplier`
+pub const QUEUE_SPEED_MIN: f64 = 0.1;
+pub const QUEUE_SPEED_MAX: f64 = 10.0;
+
+impl FolderPlaybackQueue {
+    /// Apply the queue's speed multiplier to a message about to be triggered
+    pub fn apply_speed(&self, msg: &mut MessageConfig) {
+        if let Some(multiplier) = self.speed_multiplier {
+            msg.speed = Some(msg.speed.unwrap_or(1.0) * multiplier);
+        }
+    }
+
+    /// Id of the message that plays after the current one: `None` at the end of the queue,
+    /// except for previews and looping queues, which go back to the start (a shuffled loop
+    /// reshuffles when it wraps, so its next message isn't known yet)
+    pub fn next_message_id(&self) -> Option<&str> {
+        match self.message_ids.get(self.current_index + 1) {
+            Some(id) => Some(id),
+            None if self.shuffle && self.loop_playback => None,
+            None if self.preview || self.loop_playback => self.message_ids.first().map(|id| id.as_str()),
+            None => None,
+        }
+    }
+
+    /// Move past `completed_id` if it is the current message. Previews and looping queues
+    /// go back to the start after the last message (reshuffled, for a shuffled queue).
+    pub fn advance(&mut self, completed_id: &str) -> QueueAdvance {
+        if self.message_ids.get(self.current_index).map(|id| id.as_str()) != Some(completed_id) {
+            return QueueAdvance::Ignored;
+        }
+        if self.paused {
+            return QueueAdvance::Held;
+        }
+        self.current_index += 1;
+        if self.current_index >= self.message_ids.len() {
+            if !(self.preview || self.loop_playback) {
+                return QueueAdvance::Finished;
+            }
+            self.current_index = 0;
+            if self.shuffle {
+                self.shuffle_order(Some(completed_id));
+            }
+        }
+        match self.message_ids.get(self.current_index) {
+            Some(id) => QueueAdvance::Next(id.clone()),
+            None => QueueAdvance::Finished,
+        }
+    }
+
+    /// Put the messages in a new random order that doesn't start with `avoid_first` (the
+    /// message that just played, when a loop wraps around) unless it is the only one
+    pub fn shuffle_order(&mut self, avoid_first: Option<&str>) {
+        fastrand::shuffle(&mut self.message_ids);
+        if self.message_ids.len() > 1 && self.message_ids.first().map(|id| id.as_str()) == avoid_first {
+            let swap_with = fastrand::usize(1..self.message_ids.len());
+            self.message_ids.swap(0, swap_with);
+        }
+    }
 }
 
 /// E2E Test Report from Frontend
@@ -107,6 +428,149 @@ pub struct RemoteCommand {
     pub payload: Option<serde_json::Value>,
 }
 
+/// A `trigger-message` payload scheduled to fire at a wall-clock time
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledMessage {
+    pub id: String,
+    /// Unix time in milliseconds
+    pub at: u64,
+    pub payload: serde_json::Value,
+    /// Skip the message instead of firing it late if it is overdue by more than this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_after_ms: Option<u64>,
+}
+
+impl ScheduledMessage {
+    /// Whether the message is too overdue to fire at `now` (Unix ms)
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_after_ms
+            .is_some_and(|expiry| now.saturating_sub(self.at) > expiry)
+    }
+}
+
+/// Countdown target for "T-minus" displays; the frontend does the rendering
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Countdown {
+    /// Unix time (ms) the countdown runs to
+    pub target: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// A message re-triggered every `interval_ms` until cancelled or `until` passes
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecurringSchedule {
+    pub id: String,
+    pub message_id: String,
+    pub interval_ms: u64,
+    /// Unix time in milliseconds after which the recurrence stops
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<u64>,
+    /// Unix time in milliseconds of the next firing
+    pub next_at: u64,
+}
+
+impl RecurringSchedule {
+    /// Move `next_at` to the first interval after `now`, skipping firings missed while the
+    /// app was asleep rather than replaying them all
+    pub fn advance(&mut self, now: u64) {
+        let interval = self.interval_ms.max(1);
+        let missed = now.saturating_sub(self.next_at) / interval;
+        self.next_at += (missed + 1) * interval;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.until.is_some_and(|until| self.next_at > until)
+    }
+}
+
+/// A command captured while recording a macro, with its offset from the start of the recording
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedCommand {
+    pub offset_ms: u64,
+    pub command: RemoteCommand,
+}
+
+/// Entry in the in-memory event log. `id` increases monotonically so clients can tail
+/// the log with an `after` cursor.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EventLogEntry {
+    pub id: u64,
+    pub timestamp: u64,
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+}
+
+/// Audit record of a remote `/api/command` request
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogEntry {
+    pub timestamp: u64,
+    pub ip: String,
+    /// Command name, or `None` if the body wasn't a readable command
+    pub command: Option<String>,
+    /// HTTP status of the response
+    pub status: u16,
+}
+
+/// One line of the persisted log file (JSONL), tagged with which log it belongs to
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PersistedLogEntry {
+    Event(EventLogEntry),
+    Access(AccessLogEntry),
+}
+
+/// Build identification reported by `/api/version`
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_hash: Option<String>,
+    /// Unix time in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_time: Option<u64>,
+    pub tauri_version: String,
+}
+
+/// Outer position and inner size of a window, in physical pixels
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where a window sits in a saved layout. The position is relative to the origin of the
+/// monitor it was on, so the layout survives monitors being rearranged.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowPlacement {
+    #[serde(flatten)]
+    pub geometry: WindowGeometry,
+    /// Monitor name; `None` if it couldn't be determined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<String>,
+    #[serde(default)]
+    pub fullscreen: bool,
+}
+
+/// Named window layout: placements by window label ("main", "viz")
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowLayout {
+    pub windows: std::collections::BTreeMap<String, WindowPlacement>,
+}
+
 /// Application state that gets broadcast via SSE
 #[derive(Clone, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -127,44 +591,925 @@ pub struct BroadcastState {
     pub message_stats: serde_json::Value,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub triggered_message: Option<MessageConfig>,
+    /// When the triggered message started (Unix ms), for elapsed-time/progress display
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub triggered_at: Option<u64>,
+    /// Output window the triggered message is meant for ("all", "viz", "viz2", ...)
+    pub triggered_target: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub folder_playback_queue: Option<FolderPlaybackQueue>,
+    /// Messages triggered with `queue: true`, waiting to play
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub queued_messages: Vec<MessageConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub scheduled_messages: Vec<ScheduledMessage>,
+    /// Active recurring schedules, ordered by next firing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recurring_schedules: Vec<RecurringSchedule>,
+    /// Name of the current event/session (e.g. "Saturday Set")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub countdown: Option<Countdown>,
     // Legacy compatibility
     pub mode: String,
 }
 
-pub fn flatten_message_tree_value(tree: &serde_json::Value) -> Vec<MessageConfig> {
-    fn walk(node: &serde_json::Value, out: &mut Vec<MessageConfig>) {
+/// Trigger target that shows a message on every output window
+pub const TRIGGER_TARGET_ALL: &str = "all";
+
+/// A trigger target is either "all" or an output window label ("viz", "viz2", ...)
+pub fn is_valid_trigger_target(target: &str) -> bool {
+    target == TRIGGER_TARGET_ALL
+        || target.strip_prefix("viz").is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Split message text the way the visualizer does (`computeSplitSequence`): parts are trimmed,
+/// empty ones dropped, and the whole trimmed text is used if nothing remains.
+/// `mode` is `"literal"` (default) or `"regex"`; an invalid pattern is an error.
+pub fn split_message_text(text: &str, separator: &str, mode: Option<&str>) -> Result<Vec<String>, String> {
+    if separator.is_empty() {
+        return Ok(vec![text.to_string()]);
+    }
+    let raw: Vec<&str> = match mode.unwrap_or("literal") {
+        "literal" => text.split(separator).collect(),
+        "regex" => {
+            let re = regex::Regex::new(separator)
+                .map_err(|e| format!("Invalid split pattern '{}': {}", separator, e))?;
+            re.split(text).collect()
+        }
+        other => return Err(format!("Unknown split mode '{}' (expected literal or regex)", other)),
+    };
+    let parts: Vec<String> = raw.iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string())
+        .collect();
+    if parts.is_empty() {
+        Ok(vec![text.trim().to_string()])
+    } else {
+        Ok(parts)
+    }
+}
+
+/// A folder of the message tree, as listed for folder pickers
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSummary {
+    pub id: String,
+    pub name: String,
+    /// Folder names from the root down to this folder, joined with `/`
+    pub path: String,
+    /// Playable messages in the folder, including nested folders
+    pub message_count: usize,
+}
+
+/// Node of the message tree (matches the frontend `MessageTreeNode` type)
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MessageTreeNode {
+    Message {
+        /// Leaf node id (usually equals message.id)
+        #[serde(default)]
+        id: String,
+        message: MessageConfig,
+    },
+    #[serde(rename_all = "camelCase")]
+    Folder {
+        id: String,
+        #[serde(default)]
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        collapsed: Option<bool>,
+        /// Default text style for messages in this folder that don't set their own
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default_text_style: Option<String>,
+        #[serde(default)]
+        children: Vec<MessageTreeNode>,
+    },
+}
+
+impl MessageTreeNode {
+    /// Parse a single node, dropping (rather than failing on) children that don't parse
+    fn from_value(value: &serde_json::Value) -> Option<Self> {
+        match value.get("type").and_then(|v| v.as_str())? {
+            "message" => serde_json::from_value(value.clone()).ok(),
+            "folder" => {
+                let mut folder = value.clone();
+                let children = folder.as_object_mut()?
+                    .insert("children".to_string(), serde_json::json!([]))
+                    .unwrap_or(serde_json::Value::Null);
+                let mut node: MessageTreeNode = serde_json::from_value(folder).ok()?;
+                if let MessageTreeNode::Folder { children: typed, .. } = &mut node {
+                    *typed = MessageTree::from_value(&children).0;
+                }
+                Some(node)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Typed message tree: the top-level list of nodes
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct MessageTree(pub Vec<MessageTreeNode>);
+
+impl MessageTree {
+    /// Parse a raw tree, skipping nodes that don't parse
+    pub fn from_value(tree: &serde_json::Value) -> Self {
+        MessageTree(
+            tree.as_array()
+                .map(|arr| arr.iter().filter_map(MessageTreeNode::from_value).collect())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// All messages in tree order, with folder `defaultTextStyle`s applied to
+    /// messages that don't specify their own style. A message with no style of its own and
+    /// no folder default can't be displayed and is left out.
+    pub fn flatten(&self) -> Vec<MessageConfig> {
+        let mut out = Vec::new();
+        flatten_nodes(&self.0, None, &mut out);
+        out
+    }
+
+    /// Messages below a folder (including nested folders), in tree order, with inherited
+    /// styles applied; like `flatten`, unstyled messages are left out. `None` if the folder
+    /// doesn't exist.
+    pub fn collect_folder(&self, folder_id: &str) -> Option<Vec<MessageConfig>> {
+        fn walk(nodes: &[MessageTreeNode], folder_id: &str, inherited: Option<&str>) -> Option<Vec<MessageConfig>> {
+            nodes.iter().find_map(|node| match node {
+                MessageTreeNode::Folder { id, default_text_style, children, .. } => {
+                    let folder_default = default_text_style.as_deref().or(inherited);
+                    if id == folder_id {
+                        let mut out = Vec::new();
+                        flatten_nodes(children, folder_default, &mut out);
+                        Some(out)
+                    } else {
+                        walk(children, folder_id, folder_default)
+                    }
+                }
+                MessageTreeNode::Message { .. } => None,
+            })
+        }
+        walk(&self.0, folder_id, None)
+    }
+
+    /// Find a folder node by id anywhere in the tree
+    pub fn find_folder(&self, folder_id: &str) -> Option<&MessageTreeNode> {
+        fn walk<'a>(nodes: &'a [MessageTreeNode], folder_id: &str) -> Option<&'a MessageTreeNode> {
+            nodes.iter().find_map(|node| match node {
+                MessageTreeNode::Folder { id, children, .. } => {
+                    if id == folder_id {
+                        Some(node)
+                    } else {
+                        walk(children, folder_id)
+                    }
+                }
+                MessageTreeNode::Message { .. } => None,
+            })
+        }
+        walk(&self.0, folder_id)
+    }
+
+    /// Every folder in the tree, depth-first in tree order
+    pub fn folders(&self) -> Vec<FolderSummary> {
+        fn walk(nodes: &[MessageTreeNode], parent_path: &str, inherited: Option<&str>, out: &mut Vec<FolderSummary>) {
+            for node in nodes {
+                if let MessageTreeNode::Folder { id, name, default_text_style, children, .. } = node {
+                    let path = if parent_path.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}/{}", parent_path, name)
+                    };
+                    let folder_default = default_text_style.as_deref().or(inherited);
+                    let mut messages = Vec::new();
+                    flatten_nodes(children, folder_default, &mut messages);
+                    out.push(FolderSummary {
+                        id: id.clone(),
+                        name: name.clone(),
+                        path: path.clone(),
+                        message_count: messages.len(),
+                    });
+                    walk(children, &path, folder_default, out);
+                }
+            }
+        }
+        let mut out = Vec::new();
+        walk(&self.0, "", None, &mut out);
+        out
+    }
+
+    /// Every message in tree order with the `/`-joined names of its folders ("" at top level)
+    pub fn messages_with_folder_path(&self) -> Vec<(&MessageConfig, String)> {
+        fn walk<'a>(nodes: &'a [MessageTreeNode], path: &str, out: &mut Vec<(&'a MessageConfig, String)>) {
+            for node in nodes {
+                match node {
+                    MessageTreeNode::Message { message, .. } => out.push((message, path.to_string())),
+                    MessageTreeNode::Folder { name, children, .. } => {
+                        let path = if path.is_empty() { name.clone() } else { format!("{}/{}", path, name) };
+                        walk(children, &path, out);
+                    }
+                }
+            }
+        }
+        let mut out = Vec::new();
+        walk(&self.0, "", &mut out);
+        out
+    }
+
+    /// Nested Markdown list of the tree in order: folders in bold, messages with their style
+    pub fn to_markdown(&self) -> String {
+        fn walk(nodes: &[MessageTreeNode], depth: usize, out: &mut String) {
+            let indent = "  ".repeat(depth);
+            for node in nodes {
+                match node {
+                    MessageTreeNode::Folder { name, children, .. } => {
+                        let name = if name.trim().is_empty() { "(untitled folder)" } else { name.trim() };
+                        out.push_str(&format!("{}- **{}**\n", indent, name));
+                        walk(children, depth + 1, out);
+                    }
+                    MessageTreeNode::Message { message, .. } => {
+                        let text = message.text.split_whitespace().collect::<Vec<_>>().join(" ");
+                        let text = match (&message.text_file, text.is_empty()) {
+                            (Some(file), true) => format!("_(from {})_", file),
+                            _ => text,
+                        };
+                        let style = match &message.text_style_preset {
+                            Some(preset) => format!("{} / {}", message.text_style, preset),
+                            None => message.text_style.clone(),
+                        };
+                        out.push_str(&format!("{}- {} `{}`\n", indent, text, style));
+                    }
+                }
+            }
+        }
+        let mut out = String::new();
+        walk(&self.0, 0, &mut out);
+        out
+    }
+
+    /// Number of folder nodes anywhere in the tree
+    pub fn folder_count(&self) -> usize {
+        fn walk(nodes: &[MessageTreeNode]) -> usize {
+            nodes.iter()
+                .map(|node| match node {
+                    MessageTreeNode::Folder { children, .. } => 1 + walk(children),
+                    MessageTreeNode::Message { .. } => 0,
+                })
+                .sum()
+        }
+        walk(&self.0)
+    }
+
+    /// The nearest ancestor folder `defaultTextStyle` for a message
+    pub fn inherited_text_style(&self, message_id: &str) -> Option<String> {
+        fn walk(nodes: &[MessageTreeNode], message_id: &str, inherited: Option<&str>) -> Option<Option<String>> {
+            nodes.iter().find_map(|node| match node {
+                MessageTreeNode::Message { message, .. } => {
+                    (message.id == message_id).then(|| inherited.map(|s| s.to_string()))
+                }
+                MessageTreeNode::Folder { default_text_style, children, .. } => {
+                    walk(children, message_id, default_text_style.as_deref().or(inherited))
+                }
+            })
+        }
+        walk(&self.0, message_id, None).flatten()
+    }
+}
+
+fn flatten_nodes(nodes: &[MessageTreeNode], inherited: Option<&str>, out: &mut Vec<MessageConfig>) {
+    for node in nodes {
         match node {
-            serde_json::Value::Array(arr) => {
-                for n in arr {
-                    walk(n, out);
+            MessageTreeNode::Message { message, .. } => {
+                let mut message = message.clone();
+                if message.text_style.is_empty() {
+                    // Messages need a style, either their own or a folder default
+                    let Some(style) = inherited else { continue };
+                    message.text_style = style.to_string();
                 }
+                out.push(message);
             }
-            serde_json::Value::Object(obj) => {
-                if let Some(t) = obj.get("type").and_then(|v| v.as_str()) {
-                    match t {
-                        "message" => {
-                            if let Some(msg_val) = obj.get("message") {
-                                if let Ok(msg) = serde_json::from_value::<MessageConfig>(msg_val.clone()) {
-                                    out.push(msg);
-                                }
-                            }
-                        }
-                        "folder" => {
-                            if let Some(children) = obj.get("children") {
-                                walk(children, out);
-                            }
+            MessageTreeNode::Folder { default_text_style, children, .. } => {
+                flatten_nodes(children, default_text_style.as_deref().or(inherited), out);
+            }
+        }
+    }
+}
+
+/// Flatten a raw message tree into messages, applying folder `defaultTextStyle`s
+/// to messages that don't specify their own style.
+pub fn flatten_message_tree_value(tree: &serde_json::Value) -> Vec<MessageConfig> {
+    MessageTree::from_value(tree).flatten()
+}
+
+/// Find the nearest ancestor folder `defaultTextStyle` for a message in a raw tree
+pub fn inherited_text_style(tree: &serde_json::Value, message_id: &str) -> Option<String> {
+    MessageTree::from_value(tree).inherited_text_style(message_id)
+}
+
+/// Deep-merge `patch` into `target` (JSON merge patch): objects merge recursively,
+/// `null` values remove keys, and anything else replaces the existing value.
+pub fn merge_json_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch_obj) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    if let serde_json::Value::Object(target_obj) = target {
+        for (key, value) in patch_obj {
+            if value.is_null() {
+                target_obj.remove(key);
+            } else {
+                merge_json_patch(target_obj.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Count trigger `history` timestamps (Unix ms) across a message stats blob in buckets of
+/// `bucket_ms`, aligned to the Unix epoch (so UTC hours/days).
+/// Returns `(bucket start, count)` pairs in time order; empty buckets are omitted.
+pub fn trigger_histogram(stats: &serde_json::Value, bucket_ms: u64) -> Vec<(u64, u64)> {
+    let mut buckets = std::collections::BTreeMap::new();
+    let timestamps = stats.as_object()
+        .into_iter()
+        .flat_map(|obj| obj.values())
+        .filter_map(|entry| entry.get("history").and_then(|h| h.as_array()))
+        .flatten()
+        .filter_map(|h| h.get("timestamp").and_then(|t| t.as_u64()));
+    for ts in timestamps {
+        *buckets.entry(ts - ts % bucket_ms.max(1)).or_insert(0) += 1;
+    }
+    buckets.into_iter().collect()
+}
+
+/// Apply `update` to the raw `message` object of the tree node with the given message id.
+/// Returns true if the message was found.
+/// Edits the raw JSON (rather than the typed tree) so fields the backend doesn't model survive.
+pub fn update_tree_message(
+    tree: &mut serde_json::Value,
+    message_id: &str,
+    update: &mut dyn FnMut(&mut serde_json::Map<String, serde_json::Value>),
+) -> bool {
+    match tree {
+        serde_json::Value::Array(arr) => {
+            let mut found = false;
+            for n in arr.iter_mut() {
+                found |= update_tree_message(n, message_id, update);
+            }
+            found
+        }
+        serde_json::Value::Object(obj) => match obj.get("type").and_then(|v| v.as_str()) {
+            Some("message") => {
+                let Some(msg) = obj.get_mut("message").and_then(|m| m.as_object_mut()) else {
+                    return false;
+                };
+                if msg.get("id").and_then(|v| v.as_str()) == Some(message_id) {
+                    update(msg);
+                    true
+                } else {
+                    false
+                }
+            }
+            Some("folder") => obj.get_mut("children")
+                .map(|c| update_tree_message(c, message_id, update))
+                .unwrap_or(false),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Remove every message node whose message id is in `ids`, leaving folders in place.
+/// Returns the number of nodes removed.
+pub fn remove_tree_messages(tree: &mut serde_json::Value, ids: &std::collections::HashSet<String>) -> usize {
+    let Some(nodes) = tree.as_array_mut() else {
+        return 0;
+    };
+    let before = nodes.len();
+    nodes.retain(|n| {
+        n.get("type").and_then(|v| v.as_str()) != Some("message")
+            || !n.get("message")
+                .and_then(|m| m.get("id"))
+                .and_then(|v| v.as_str())
+                .is_some_and(|id| ids.contains(id))
+    });
+    let mut removed = before - nodes.len();
+    for n in nodes.iter_mut() {
+        if n.get("type").and_then(|v| v.as_str()) == Some("folder") {
+            if let Some(children) = n.get_mut("children") {
+                removed += remove_tree_messages(children, ids);
+            }
+        }
+    }
+    removed
+}
+
+/// Ids of every message node below the folder with `folder_id` in a raw tree (including
+/// nested folders), in tree order. Unlike `MessageTree::collect_folder` this includes
+/// messages without any style. `None` if the folder doesn't exist.
+pub fn folder_message_ids(tree: &serde_json::Value, folder_id: &str) -> Option<Vec<String>> {
+    fn collect(nodes: &serde_json::Value, out: &mut Vec<String>) {
+        for node in nodes.as_array().into_iter().flatten() {
+            if let Some(id) = tree_node_message_id(node) {
+                out.push(id.to_string());
+            } else if let Some(children) = node.get("children") {
+                collect(children, out);
+            }
+        }
+    }
+    for node in tree.as_array().into_iter().flatten() {
+        if is_tree_folder(node, folder_id) {
+            let mut out = Vec::new();
+            if let Some(children) = node.get("children") {
+                collect(children, &mut out);
+            }
+            return Some(out);
+        }
+        if let Some(found) = node.get("children").and_then(|c| folder_message_ids(c, folder_id)) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Message id of a raw tree node, if it is a message node
+fn tree_node_message_id(node: &serde_json::Value) -> Option<&str> {
+    if node.get("type").and_then(|v| v.as_str()) != Some("message") {
+        return None;
+    }
+    node.get("message").and_then(|m| m.get("id")).and_then(|v| v.as_str())
+}
+
+fn is_tree_folder(node: &serde_json::Value, folder_id: &str) -> bool {
+    node.get("type").and_then(|v| v.as_str()) == Some("folder")
+        && node.get("id").and_then(|v| v.as_str()) == Some(folder_id)
+}
+
+/// Children of the folder with `folder_id` in raw tree `nodes` (at any depth).
+/// A folder without a `children` array gets an empty one.
+fn tree_folder_children_mut<'a>(nodes: &'a mut [serde_json::Value], folder_id: &str) -> Option<&'a mut Vec<serde_json::Value>> {
+    for node in nodes.iter_mut() {
+        if node.get("type").and_then(|v| v.as_str()) != Some("folder") {
+            continue;
+        }
+        if is_tree_folder(node, folder_id) {
+            let obj = node.as_object_mut()?;
+            let children = obj.entry("children").or_insert_with(|| serde_json::json!([]));
+            if !children.is_array() {
+                *children = serde_json::json!([]);
+            }
+            return children.as_array_mut();
+        }
+        if let Some(found) = node.get_mut("children")
+            .and_then(|c| c.as_array_mut())
+            .and_then(|c| tree_folder_children_mut(c, folder_id))
+        {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Number of message nodes for `message_id` in raw tree `nodes` (at any depth)
+fn count_tree_message(nodes: &[serde_json::Value], message_id: &str) -> usize {
+    nodes.iter()
+        .map(|n| {
+            let own = usize::from(tree_node_message_id(n) == Some(message_id));
+            let nested = n.get("children")
+                .and_then(|c| c.as_array())
+                .map(|c| count_tree_message(c, message_id))
+                .unwrap_or(0);
+            own + nested
+        })
+        .sum()
+}
+
+/// Remove and return the first message node for `message_id` in raw tree `nodes` (at any depth)
+fn take_tree_message(nodes: &mut Vec<serde_json::Value>, message_id: &str) -> Option<serde_json::Value> {
+    if let Some(pos) = nodes.iter().position(|n| tree_node_message_id(n) == Some(message_id)) {
+        return Some(nodes.remove(pos));
+    }
+    nodes.iter_mut()
+        .filter_map(|n| n.get_mut("children").and_then(|c| c.as_array_mut()))
+        .find_map(|children| take_tree_message(children, message_id))
+}
+
+/// Move a message node to `target_index` among the children of `target_folder_id`
+/// (`None` for the top level). The index counts positions after the node is taken out,
+/// and is clamped to the end. Fails if the message or folder doesn't exist, or if the
+/// message appears more than once (moving one copy would leave a duplicate behind).
+pub fn move_tree_message(
+    tree: &mut serde_json::Value,
+    message_id: &str,
+    target_folder_id: Option<&str>,
+    target_index: usize,
+) -> Result<(), String> {
+    let Some(nodes) = tree.as_array_mut() else {
+        return Err("Message tree is not a list".to_string());
+    };
+    match count_tree_message(nodes, message_id) {
+        0 => return Err(format!("Unknown message '{}'", message_id)),
+        1 => {}
+        n => return Err(format!("Message '{}' appears {} times in the tree", message_id, n)),
+    }
+    if let Some(folder_id) = target_folder_id {
+        if tree_folder_children_mut(nodes, folder_id).is_none() {
+            return Err(format!("Unknown folder '{}'", folder_id));
+        }
+    }
+    let node = take_tree_message(nodes, message_id)
+        .ok_or_else(|| format!("Unknown message '{}'", message_id))?;
+    let target = match target_folder_id {
+        Some(folder_id) => tree_folder_children_mut(nodes, folder_id)
+            .ok_or_else(|| format!("Unknown folder '{}'", folder_id))?,
+        None => nodes,
+    };
+    target.insert(target_index.min(target.len()), node);
+    Ok(())
+}
+
+/// What `repair_message_tree` changed
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageTreeRepairReport {
+    /// Ids (or `null`) of tree nodes dropped because they didn't parse
+    pub removed_nodes: Vec<Option<String>>,
+    /// Message ids that appeared more than once in the tree; later copies were dropped
+    pub duplicate_ids: Vec<String>,
+    /// Messages from the flat list that were missing from the tree and appended at the top level
+    pub added_to_tree: Vec<String>,
+}
+
+impl MessageTreeRepairReport {
+    pub fn is_clean(&self) -> bool {
+        self.removed_nodes.is_empty() && self.duplicate_ids.is_empty() && self.added_to_tree.is_empty()
+    }
+}
+
+/// Reconcile a raw tree with the flat message list: drop nodes that don't parse and duplicate
+/// message ids, then append flat-list messages the tree is missing. The flat list should be
+/// regenerated from the repaired tree afterwards.
+/// Works on the raw JSON so fields the backend doesn't model survive.
+pub fn repair_message_tree(tree: &mut serde_json::Value, messages: &[MessageConfig]) -> MessageTreeRepairReport {
+    fn walk(
+        nodes: &mut Vec<serde_json::Value>,
+        seen: &mut std::collections::HashSet<String>,
+        report: &mut MessageTreeRepairReport,
+    ) {
+        nodes.retain_mut(|node| {
+            let Some(typed) = MessageTreeNode::from_value(node) else {
+                let id = node.get("id").and_then(|v| v.as_str()).map(|s| s.to_string());
+                report.removed_nodes.push(id);
+                return false;
+            };
+            match typed {
+                MessageTreeNode::Message { message, .. } => {
+                    if seen.insert(message.id.clone()) {
+                        true
+                    } else {
+                        report.duplicate_ids.push(message.id);
+                        false
+                    }
+                }
+                MessageTreeNode::Folder { .. } => {
+                    match node.get_mut("children").and_then(|c| c.as_array_mut()) {
+                        Some(children) => walk(children, seen, report),
+                        None => {
+                            node["children"] = serde_json::json!([]);
                         }
-                        _ => {}
                     }
+                    true
                 }
             }
-            _ => {}
+        });
+    }
+
+    let mut report = MessageTreeRepairReport::default();
+    if !tree.is_array() {
+        *tree = serde_json::json!([]);
+    }
+    let mut seen = std::collections::HashSet::new();
+    if let Some(nodes) = tree.as_array_mut() {
+        walk(nodes, &mut seen, &mut report);
+        for msg in messages {
+            if seen.insert(msg.id.clone()) {
+                nodes.push(serde_json::json!({
+                    "type": "message",
+                    "id": msg.id,
+                    "message": msg,
+                }));
+                report.added_to_tree.push(msg.id.clone());
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Same tree as the default state (`AppStateSync::new`)
+    fn default_tree() -> serde_json::Value {
+        serde_json::json!([
+            {
+                "type": "folder",
+                "id": "party-countdown",
+                "name": "Party Countdown",
+                "children": [
+                    { "type": "message", "id": "msg-1", "message": { "id": "msg-1", "text": "Countdown initiated...", "textStyle": "typewriter" } },
+                    { "type": "message", "id": "msg-2", "message": { "id": "msg-2", "text": "3, 2, 1", "textStyle": "bounce", "splitEnabled": true, "splitSeparator": ",", "speed": 1.0 } },
+                    { "type": "message", "id": "msg-3", "message": { "id": "msg-3", "text": "It's time to party 🥳", "textStyle": "scrolling-capitals", "textStylePreset": "scrolling-capitals-centered" } }
+                ]
+            }
+        ])
+    }
+
+    fn ids(messages: &[MessageConfig]) -> Vec<&str> {
+        messages.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    #[test]
+    fn default_tree_flattens_in_order() {
+        let tree = MessageTree::from_value(&default_tree());
+        let flat = tree.flatten();
+        assert_eq!(ids(&flat), ["msg-1", "msg-2", "msg-3"]);
+        assert_eq!(flat[1].text_style, "bounce");
+        assert_eq!(flat[2].text_style_preset.as_deref(), Some("scrolling-capitals-centered"));
+    }
+
+    #[test]
+    fn default_tree_collects_folder() {
+        let tree = MessageTree::from_value(&default_tree());
+        assert_eq!(ids(&tree.collect_folder("party-countdown").unwrap()), ["msg-1", "msg-2", "msg-3"]);
+        assert!(tree.collect_folder("missing").is_none());
+    }
+
+    #[test]
+    fn default_tree_lists_folders() {
+        let folders = MessageTree::from_value(&default_tree()).folders();
+        assert_eq!(folders.len(), 1);
+        assert_eq!(folders[0].id, "party-countdown");
+        assert_eq!(folders[0].path, "Party Countdown");
+        assert_eq!(folders[0].message_count, 3);
+    }
+
+    #[test]
+    fn flatten_leaves_out_messages_without_any_style() {
+        let tree = MessageTree::from_value(&serde_json::json!([
+            { "type": "message", "id": "styled", "message": { "id": "styled", "text": "a", "textStyle": "fade" } },
+            { "type": "message", "id": "unstyled", "message": { "id": "unstyled", "text": "b" } }
+        ]));
+        assert_eq!(ids(&tree.flatten()), ["styled"]);
+    }
+
+    fn message_node(id: &str) -> serde_json::Value {
+        serde_json::json!({ "type": "message", "id": id, "message": { "id": id, "text": id, "textStyle": "fade" } })
+    }
+
+    fn message(id: &str) -> MessageConfig {
+        serde_json::from_value(serde_json::json!({ "id": id, "text": id, "textStyle": "fade" })).unwrap()
+    }
+
+    #[test]
+    fn repair_drops_duplicate_ids() {
+        let mut tree = serde_json::json!([
+            message_node("a"),
+            { "type": "folder", "id": "f", "name": "F", "children": [message_node("a"), message_node("b")] }
+        ]);
+        let report = repair_message_tree(&mut tree, &[message("a"), message("b")]);
+        assert_eq!(report.duplicate_ids, ["a"]);
+        assert!(report.removed_nodes.is_empty() && report.added_to_tree.is_empty());
+        assert_eq!(ids(&flatten_message_tree_value(&tree)), ["a", "b"]);
+    }
+
+    #[test]
+    fn repair_appends_messages_missing_from_tree() {
+        let mut tree = serde_json::json!([message_node("a")]);
+        let report = repair_message_tree(&mut tree, &[message("a"), message("orphan")]);
+        assert_eq!(report.added_to_tree, ["orphan"]);
+        assert_eq!(ids(&flatten_message_tree_value(&tree)), ["a", "orphan"]);
+    }
+
+    #[test]
+    fn repair_removes_malformed_nodes() {
+        let mut tree = serde_json::json!([
+            { "type": "folder", "name": "No id", "children": [] },
+            { "type": "message", "id": "bad", "message": "not a message" },
+            { "type": "folder", "id": "f", "name": "No children" },
+            message_node("a")
+        ]);
+        let report = repair_message_tree(&mut tree, &[message("a")]);
+        assert_eq!(report.removed_nodes, [None, Some("bad".to_string())]);
+        assert_eq!(tree[0]["id"], "f");
+        assert_eq!(tree[0]["children"], serde_json::json!([]));
+        assert_eq!(ids(&flatten_message_tree_value(&tree)), ["a"]);
+
+        let mut not_a_list = serde_json::json!({ "type": "folder" });
+        repair_message_tree(&mut not_a_list, &[message("a")]);
+        assert_eq!(ids(&flatten_message_tree_value(&not_a_list)), ["a"]);
+    }
+
+    /// Top-level `a`, then folder `outer` holding `b` and folder `inner` (holding `c`)
+    fn nested_tree() -> serde_json::Value {
+        serde_json::json!([
+            message_node("a"),
+            { "type": "folder", "id": "outer", "name": "Outer", "children": [
+                message_node("b"),
+                { "type": "folder", "id": "inner", "name": "Inner", "children": [message_node("c")] }
+            ] }
+        ])
+    }
+
+    #[test]
+    fn move_into_nested_folder() {
+        let mut tree = nested_tree();
+        move_tree_message(&mut tree, "a", Some("inner"), 0).unwrap();
+        let tree = MessageTree::from_value(&tree);
+        assert_eq!(ids(&tree.collect_folder("inner").unwrap()), ["a", "c"]);
+        assert_eq!(ids(&tree.flatten()), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn move_to_root_clamps_index() {
+        let mut tree = nested_tree();
+        move_tree_message(&mut tree, "c", None, 99).unwrap();
+        let tree = MessageTree::from_value(&tree);
+        assert_eq!(ids(&tree.flatten()), ["a", "b", "c"]);
+        assert!(tree.collect_folder("inner").unwrap().is_empty());
+        assert_eq!(tree.0.len(), 3);
+    }
+
+    #[test]
+    fn move_rejects_missing_ids() {
+        let mut tree = nested_tree();
+        assert!(move_tree_message(&mut tree, "missing", None, 0).is_err());
+        assert!(move_tree_message(&mut tree, "a", Some("missing"), 0).is_err());
+        assert_eq!(tree, nested_tree());
+    }
+
+    #[test]
+    fn split_with_empty_separator_keeps_text_whole() {
+        assert_eq!(split_message_text("3, 2, 1", "", None).unwrap(), ["3, 2, 1"]);
+    }
+
+    #[test]
+    fn split_drops_trailing_and_whitespace_only_parts() {
+        assert_eq!(split_message_text("3, 2, 1,", ",", None).unwrap(), ["3", "2", "1"]);
+        assert_eq!(split_message_text("a,  ,b,,", ",", Some("literal")).unwrap(), ["a", "b"]);
+        assert_eq!(split_message_text("a1b22c", r"\d+", Some("regex")).unwrap(), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn split_with_only_separators_falls_back_to_trimmed_text() {
+        assert_eq!(split_message_text(" , , ", ",", None).unwrap(), [", ,"]);
+        assert!(split_message_text("a", "(", Some("regex")).is_err());
+        assert!(split_message_text("a", ",", Some("glob")).is_err());
+    }
+
+    #[test]
+    fn histogram_buckets_align_to_boundaries() {
+        const MINUTE: u64 = 60_000;
+        const HOUR: u64 = 3_600_000;
+        const DAY: u64 = 86_400_000;
+        let day = 20_000 * DAY;
+        // Last and first millisecond either side of a day (and so hour and minute) boundary
+        let stats = serde_json::json!({
+            "a": { "history": [{ "timestamp": day - 1 }, { "timestamp": day }] },
+            "b": { "history": [{ "timestamp": day + MINUTE - 1 }, { "timestamp": day + MINUTE }] },
+            "c": { "history": [{ "timestamp": day + HOUR }] }
+        });
+        assert_eq!(trigger_histogram(&stats, MINUTE), [
+            (day - MINUTE, 1),
+            (day, 2),
+            (day + MINUTE, 1),
+            (day + HOUR, 1),
+        ]);
+        assert_eq!(trigger_histogram(&stats, HOUR), [(day - HOUR, 1), (day, 3), (day + HOUR, 1)]);
+        assert_eq!(trigger_histogram(&stats, DAY), [(day - DAY, 1), (day, 4)]);
+        assert!(trigger_histogram(&serde_json::json!({}), HOUR).is_empty());
+    }
+
+    #[test]
+    fn theme_recolors_only_existing_color_settings() {
+        let theme = Theme {
+            accent: Some("#ff0000".to_string()),
+            background: None,
+            text: Some("#ffffff".to_string()),
+        };
+        let mut settings = serde_json::json!({ "glowColor": "#00ff00", "color": "#ffffff", "bgColor": "#000000", "speed": 1 });
+        assert_eq!(theme.apply_to_settings(&mut settings), 1);
+        assert_eq!(
+            settings,
+            serde_json::json!({ "glowColor": "#ff0000", "color": "#ffffff", "bgColor": "#000000", "speed": 1 })
+        );
+        assert_eq!(theme.apply_to_settings(&mut settings), 0);
+    }
+
+    #[test]
+    fn folder_message_ids_include_unstyled_and_nested_messages() {
+        let tree = serde_json::json!([
+            { "type": "message", "id": "top", "message": { "id": "top", "text": "Top", "textStyle": "fade" } },
+            { "type": "folder", "id": "outer", "name": "Outer", "children": [
+                { "type": "message", "id": "plain", "message": { "id": "plain", "text": "No style" } },
+                { "type": "folder", "id": "inner", "name": "Inner", "children": [
+                    { "type": "message", "id": "deep", "message": { "id": "deep", "text": "Deep", "textStyle": "bounce" } }
+                ] }
+            ] }
+        ]);
+        assert_eq!(folder_message_ids(&tree, "outer"), Some(vec!["plain".to_string(), "deep".to_string()]));
+        assert_eq!(folder_message_ids(&tree, "inner"), Some(vec!["deep".to_string()]));
+        assert_eq!(MessageTree::from_value(&tree).collect_folder("outer").map(|m| m.len()), Some(1));
+        assert_eq!(folder_message_ids(&tree, "missing"), None);
+    }
+
+    #[test]
+    fn nearest_folder_default_style_is_inherited() {
+        let tree = serde_json::json!([
+            { "type": "folder", "id": "outer", "name": "Outer", "defaultTextStyle": "fade", "children": [
+                { "type": "message", "id": "plain", "message": { "id": "plain", "text": "a" } },
+                { "type": "message", "id": "own", "message": { "id": "own", "text": "b", "textStyle": "bounce" } },
+                { "type": "folder", "id": "inner", "name": "Inner", "defaultTextStyle": "typewriter", "children": [
+                    { "type": "message", "id": "deep", "message": { "id": "deep", "text": "c" } }
+                ] }
+            ] }
+        ]);
+        let styles: Vec<(String, String)> = flatten_message_tree_value(&tree)
+            .into_iter()
+            .map(|m| (m.id, m.text_style))
+            .collect();
+        assert_eq!(styles, [
+            ("plain".to_string(), "fade".to_string()),
+            ("own".to_string(), "bounce".to_string()),
+            ("deep".to_string(), "typewriter".to_string()),
+        ]);
+        assert_eq!(inherited_text_style(&tree, "plain").as_deref(), Some("fade"));
+        assert_eq!(inherited_text_style(&tree, "deep").as_deref(), Some("typewriter"));
+        assert_eq!(inherited_text_style(&tree, "missing"), None);
+
+        // Resolved at flatten time, not stored back into the tree
+        assert!(tree[0]["children"][0]["message"].get("textStyle").is_none());
+    }
+
+    #[test]
+    fn aspect_ratios_need_two_positive_numbers() {
+        for valid in ["16:9", "4:3", "2.39:1", " 21 : 9 "] {
+            assert!(is_valid_aspect(valid), "{} should be valid", valid);
+        }
+        for invalid in ["16x9", "16", "0:9", "-4:3", "4:0", ":", "a:b", "inf:1", "NaN:1", ""] {
+            assert!(!is_valid_aspect(invalid), "{} should be invalid", invalid);
+        }
+    }
+
+    fn queue(ids: &[&str]) -> FolderPlaybackQueue {
+        FolderPlaybackQueue {
+            folder_id: "party-countdown".to_string(),
+            message_ids: ids.iter().map(|id| id.to_string()).collect(),
+            current_index: 0,
+            preview: false,
+            speed_multiplier: None,
+            paused: false,
+            shuffle: false,
+            loop_playback: false,
         }
     }
 
-    let mut out = vec![];
-    walk(tree, &mut out);
-    out
+    #[test]
+    fn paused_queue_holds_on_completion() {
+        let mut q = queue(&["msg-1", "msg-2"]);
+        q.paused = true;
+        assert_eq!(q.advance("msg-1"), QueueAdvance::Held);
+        assert_eq!(q.current_index, 0);
+
+        q.paused = false;
+        assert_eq!(q.advance("msg-2"), QueueAdvance::Ignored);
+        assert_eq!(q.advance("msg-1"), QueueAdvance::Next("msg-2".to_string()));
+        assert_eq!(q.advance("msg-2"), QueueAdvance::Finished);
+
+        let mut looping = queue(&["msg-1", "msg-2"]);
+        looping.loop_playback = true;
+        looping.current_index = 1;
+        assert_eq!(looping.advance("msg-2"), QueueAdvance::Next("msg-1".to_string()));
+        assert_eq!(looping.current_index, 0);
+    }
+
+    #[test]
+    fn overdue_scheduled_message_expires() {
+        let mut scheduled = ScheduledMessage {
+            id: "sched-1".to_string(),
+            at: 10_000,
+            payload: serde_json::json!({ "id": "msg-1" }),
+            expires_after_ms: Some(5_000),
+        };
+        assert!(!scheduled.is_expired(9_000));
+        assert!(!scheduled.is_expired(15_000));
+        assert!(scheduled.is_expired(15_001));
+        scheduled.expires_after_ms = None;
+        assert!(!scheduled.is_expired(u64::MAX));
+    }
 }
@@ -0,0 +1,73 @@
+//! Capture dates for chronological slideshows (`list_images?sort=exif_date`): EXIF
+//! `DateTimeOriginal` via the `kamadak-exif` crate, falling back to the file mtime.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parsed capture dates keyed by path, with the file mtime they were read at
+pub type ExifDateCache = HashMap<PathBuf, (SystemTime, Option<i64>)>;
+
+/// EXIF `DateTimeOriginal` of an image as Unix seconds. EXIF dates carry no time zone,
+/// so they are read as UTC; that keeps photos from one camera in order.
+pub fn exif_capture_time(path: &Path) -> Option<i64> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    let exif::Value::Ascii(ref values) = field.value else {
+        return None;
+    };
+    let dt = exif::DateTime::from_ascii(values.first()?).ok()?;
+    if !(1..=12).contains(&dt.month) || !(1..=31).contains(&dt.day) {
+        return None;
+    }
+    let days = days_from_civil(dt.year as i64, dt.month as i64, dt.day as i64);
+    Some(days * 86_400 + dt.hour as i64 * 3_600 + dt.minute as i64 * 60 + dt.second as i64)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Sort `files` by capture date (EXIF, else mtime), oldest first; ties keep name order.
+/// EXIF results are cached per path and mtime, so re-listing a folder doesn't re-read files.
+pub fn sort_by_capture_date(files: &mut [String], cache: &mut ExifDateCache) {
+    let mut keyed: Vec<(i64, String)> = files
+        .iter()
+        .map(|file| {
+            let path = Path::new(file);
+            let mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            let exif_time = match mtime {
+                Some(mtime) => match cache.get(path) {
+                    Some((cached_mtime, time)) if *cached_mtime == mtime => *time,
+                    _ => {
+                        let time = exif_capture_time(path);
+                        cache.insert(path.to_path_buf(), (mtime, time));
+                        time
+                    }
+                },
+                None => exif_capture_time(path),
+            };
+            let mtime_secs = mtime
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            (exif_time.unwrap_or(mtime_secs), file.clone())
+        })
+        .collect();
+    keyed.sort();
+    for (slot, (_, file)) in files.iter_mut().zip(keyed) {
+        *slot = file;
+    }
+}
@@ -0,0 +1,84 @@
+//! Bounded LRU cache of image file bytes for `serve_image`, so looping slideshows
+//! don't re-read every file from disk on each pass.
+
+use axum::body::Bytes;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// Default cache size when `VIBECAST_IMAGE_CACHE_MB` is unset
+const DEFAULT_CAPACITY_MB: usize = 64;
+
+struct CacheEntry {
+    mtime: SystemTime,
+    bytes: Bytes,
+    last_used: u64,
+}
+
+pub struct ImageCache {
+    capacity_bytes: usize,
+    used_bytes: usize,
+    clock: u64,
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl ImageCache {
+    pub fn new(capacity_bytes: usize) -> Self {
+        Self {
+            capacity_bytes,
+            used_bytes: 0,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Cache sized from `VIBECAST_IMAGE_CACHE_MB` (0 disables caching)
+    pub fn from_env() -> Self {
+        let mb = std::env::var("VIBECAST_IMAGE_CACHE_MB")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_CAPACITY_MB);
+        Self::new(mb * 1024 * 1024)
+    }
+
+    /// Cached bytes for `path`, if present and still matching the file's `mtime`
+    pub fn get(&mut self, path: &str, mtime: SystemTime) -> Option<Bytes> {
+        if self.entries.get(path).is_some_and(|e| e.mtime != mtime) {
+            self.remove(path);
+            return None;
+        }
+        self.clock += 1;
+        let entry = self.entries.get_mut(path)?;
+        entry.last_used = self.clock;
+        Some(entry.bytes.clone())
+    }
+
+    /// Store bytes for `path`, evicting least recently used entries to stay within capacity
+    pub fn insert(&mut self, path: &str, mtime: SystemTime, bytes: Bytes) {
+        if bytes.len() > self.capacity_bytes {
+            return;
+        }
+        self.remove(path);
+        while self.used_bytes + bytes.len() > self.capacity_bytes {
+            let Some(oldest) = self.entries.iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+        self.clock += 1;
+        self.used_bytes += bytes.len();
+        self.entries.insert(path.to_string(), CacheEntry {
+            mtime,
+            bytes,
+            last_used: self.clock,
+        });
+    }
+
+    fn remove(&mut self, path: &str) {
+        if let Some(entry) = self.entries.remove(path) {
+            self.used_bytes -= entry.bytes.len();
+        }
+    }
+}
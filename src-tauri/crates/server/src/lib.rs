@@ -1,5 +1,8 @@
 use axum::{
-    extract::{Query, State},
+    async_trait,
+    body::Body,
+    extract::{rejection::JsonRejection, ConnectInfo, FromRequest, Path as AxumPath, Query, Request, State},
+    middleware::{self, Next},
     response::{
         sse::{Event, KeepAlive, Sse},
         Html, IntoResponse, Response,
@@ -9,20 +12,33 @@ use axum::{
     Json, Router,
 };
 use futures::{stream::Stream, StreamExt};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::path::Path;
-use std::sync::Arc;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter, Manager, path::BaseDirectory};
-use tokio_stream::wrappers::BroadcastStream;
-use tower_http::{cors::CorsLayer, services::{ServeDir, ServeFile}};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, path::BaseDirectory};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tower_http::{cors::{AllowOrigin, Any, CorsLayer}, services::{ServeDir, ServeFile}};
 
-use vibe_cast_state::AppStateSync;
+mod exif_date;
+mod image_cache;
+mod log_capture;
+mod media_probe;
+mod media_zip;
+mod palette;
+mod thumbnails;
+pub use log_capture::capture_process_output;
+
+use vibe_cast_audio::AudioState;
+use vibe_cast_state::{check_required_config, config_section_errors, AppStateSync, ACCESS_LOG_CAPACITY, COMMAND_REPLAY_CAPACITY, CONFIRM_TOKEN_TTL, EVENT_LOG_CAPACITY, RECENT_MESSAGES_CAPACITY};
 use vibe_cast_models::{
-    BroadcastState, MessageConfig, CommonSettings, VisualizationPreset, 
-    TextStylePreset, FolderPlaybackQueue, E2EReport, RemoteCommand
+    AccessLogEntry, BroadcastState, Countdown, FolderSummary, MessageConfig, MessageTree, CommonSettings, VisualizationPreset, 
+    TextStylePreset, FolderPlaybackQueue, E2EReport, QueueAdvance, RemoteCommand,
+    RecurringSchedule, ScheduledMessage, TextAudioCoupling, Theme, VersionInfo, WindowGeometry, IDLE_SPEED_MAX, IDLE_SPEED_MIN, KNOWN_TEXT_STYLES, KNOWN_VISUALIZATIONS, QUEUE_SPEED_MAX, QUEUE_SPEED_MIN, TEXT_AUDIO_COUPLING_KEY, TRIGGER_TARGET_ALL,
+    flatten_message_tree_value, folder_message_ids, inherited_text_style, is_valid_aspect, is_valid_trigger_target,
+    merge_json_patch, move_tree_message, remove_tree_messages, repair_message_tree, split_message_text, trigger_histogram, update_tree_message
 };
 
 fn resolve_path(path: &str, base_path: Option<&str>) -> String {
@@ -38,44 +54,6 @@ fn resolve_path(path: &str, base_path: Option<&str>) -> String {
     path.to_string()
 }
 
-// ... (keep existing helper functions flatten_message_tree, build_flat_message_tree, collect_messages_from_folder) ...
-
-fn flatten_message_tree(tree: &serde_json::Value) -> Vec<MessageConfig> {
-    fn walk(node: &serde_json::Value, out: &mut Vec<MessageConfig>) {
-        match node {
-            serde_json::Value::Array(arr) => {
-                for n in arr {
-                    walk(n, out);
-                }
-            }
-            serde_json::Value::Object(obj) => {
-                if let Some(t) = obj.get("type").and_then(|v| v.as_str()) {
-                    match t {
-                        "message" => {
-                            if let Some(msg_val) = obj.get("message") {
-                                if let Ok(msg) = serde_json::from_value::<MessageConfig>(msg_val.clone()) {
-                                    out.push(msg);
-                                }
-                            }
-                        }
-                        "folder" => {
-                            if let Some(children) = obj.get("children") {
-                                walk(children, out);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-
-    let mut out: Vec<MessageConfig> = vec![];
-    walk(tree, &mut out);
-    out
-}
-
 fn build_flat_message_tree(messages: &[MessageConfig]) -> serde_json::Value {
     serde_json::Value::Array(
         messages
@@ -91,84 +69,398 @@ fn build_flat_message_tree(messages: &[MessageConfig]) -> serde_json::Value {
 
 /// Collect all message IDs from a folder in the message tree
 fn collect_messages_from_folder(folder_id: &str, tree: &serde_json::Value) -> Vec<String> {
-    // First, find the folder node
-    fn find_folder<'a>(folder_id: &str, node: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
-        match node {
-            serde_json::Value::Array(arr) => {
-                for n in arr {
-                    if let Some(found) = find_folder(folder_id, n) {
-                        return Some(found);
-                    }
-                }
-                None
-            }
-            serde_json::Value::Object(obj) => {
-                if let Some(t) = obj.get("type").and_then(|v| v.as_str()) {
-                    if t == "folder" {
-                        if let Some(id) = obj.get("id").and_then(|v| v.as_str()) {
-                            if id == folder_id {
-                                return Some(node);
-                            }
-                        }
-                        // Check nested folders
-                        if let Some(children) = obj.get("children") {
-                            if let Some(found) = find_folder(folder_id, children) {
-                                return Some(found);
-                            }
-                        }
-                    }
+    MessageTree::from_value(tree)
+        .collect_folder(folder_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.id)
+        .collect()
+}
+
+/// Resolve a media reference the way `list_images` does: `$RESOURCES/` paths against the
+/// bundled resources, everything else relative to the config base path.
+fn resolve_media_path(state: &AppState, path: &str) -> Result<String, String> {
+    if let Some(subpath) = path.strip_prefix("$RESOURCES/") {
+        state.app_handle.path().resolve(subpath, BaseDirectory::Resource)
+            .map(|p| p.to_string_lossy().to_string())
+            .map_err(|e| format!("Failed to resolve resource '{}': {}", subpath, e))
+    } else {
+        let base_path_opt = state.app_state_sync.config_base_path.lock()
+            .ok()
+            .and_then(|p| p.clone());
+        Ok(resolve_path(path, base_path_opt.as_deref()))
+    }
+}
+
+/// Canonicalize `path` and check it lies inside one of `roots` (canonicalized here; missing
+/// roots are skipped). Canonicalizing resolves `..` and symlinks, so neither can be used to
+/// escape the roots.
+fn check_media_path(path: &Path, roots: &[std::path::PathBuf]) -> Result<std::path::PathBuf, StatusCode> {
+    let canonical = std::fs::canonicalize(path).map_err(|_| StatusCode::NOT_FOUND)?;
+    let allowed = roots.iter()
+        .filter_map(|root| std::fs::canonicalize(root).ok())
+        .any(|root| canonical.starts_with(root));
+    if allowed {
+        Ok(canonical)
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Folders media may be read from: the config base path, the bundled resources and every
+/// folder the app has listed media from
+fn media_roots(state: &AppState) -> Vec<std::path::PathBuf> {
+    let mut roots: Vec<std::path::PathBuf> = state.app_state_sync.media_roots.lock()
+        .map(|r| r.iter().cloned().collect())
+        .unwrap_or_default();
+    if let Some(base) = state.app_state_sync.config_base_path.lock().ok().and_then(|p| p.clone()) {
+        roots.push(base.into());
+    }
+    if let Ok(resources) = state.app_handle.path().resource_dir() {
+        roots.push(resources);
+    }
+    roots
+}
+
+/// Canonicalize a requested media file or folder and check it lies inside `media_roots`
+fn allowed_media_file(state: &AppState, path: &str) -> Result<std::path::PathBuf, StatusCode> {
+    check_media_path(Path::new(path), &media_roots(state))
+}
+
+/// Describe how a media reference resolves on this machine
+fn describe_resolved_path(state: &AppState, input: &str) -> serde_json::Value {
+    let base = state.app_state_sync.config_base_path.lock()
+        .ok()
+        .and_then(|p| p.clone());
+    match resolve_media_path(state, input) {
+        Ok(resolved) => describe_path(input, base.as_deref(), &resolved, &media_roots(state)),
+        Err(e) => serde_json::json!({
+            "input": input,
+            "base": base,
+            "resolved": null,
+            "allowed": false,
+            "exists": false,
+            "isDir": false,
+            "error": e
+        }),
+    }
+}
+
+/// `{ input, base, resolved, allowed, exists, isDir }` for a resolved path. Paths outside
+/// `roots` report `exists: false` whether or not they exist, so remote clients can't use
+/// this to probe the filesystem.
+fn describe_path(input: &str, base: Option<&str>, resolved: &str, roots: &[std::path::PathBuf]) -> serde_json::Value {
+    let allowed = check_media_path(Path::new(resolved), roots).ok();
+    serde_json::json!({
+        "input": input,
+        "base": base,
+        "resolved": resolved,
+        "allowed": allowed.is_some(),
+        "exists": allowed.is_some(),
+        "isDir": allowed.is_some_and(|p| p.is_dir())
+    })
+}
+
+/// Largest `textFile` the server will read into a message
+const MAX_TEXT_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Read a `textFile`, refusing files outside `roots` or over `MAX_TEXT_FILE_BYTES`
+fn read_text_file(path: &Path, roots: &[std::path::PathBuf]) -> Result<String, String> {
+    let canonical = check_media_path(path, roots).map_err(|status| match status {
+        StatusCode::FORBIDDEN => format!("{} is outside the allowed media folders", path.display()),
+        _ => format!("{} not found", path.display()),
+    })?;
+    let meta = std::fs::metadata(&canonical)
+        .map_err(|e| format!("Failed to stat {}: {}", canonical.display(), e))?;
+    if meta.len() > MAX_TEXT_FILE_BYTES {
+        return Err(format!("{} exceeds {} bytes, not reloading", canonical.display(), MAX_TEXT_FILE_BYTES));
+    }
+    std::fs::read_to_string(&canonical)
+        .map_err(|e| format!("Failed to read {}: {}", canonical.display(), e))
+}
+
+/// Resolved path of a message's `textFile`, if it has one
+fn text_file_path(state: &AppState, msg: &MessageConfig) -> Option<String> {
+    let file = msg.text_file.as_deref().filter(|f| !f.is_empty())?;
+    resolve_media_path(state, file)
+        .map_err(|e| eprintln!("[textFile] {}", e))
+        .ok()
+}
+
+/// Re-read a message's `textFile` into its `text`, so file-backed messages pick up edits
+/// each time they are re-triggered. Leaves the text untouched if the file can't be read.
+async fn refresh_text_file(state: &AppState, msg: &mut MessageConfig) {
+    let Some(resolved) = text_file_path(state, msg) else {
+        return;
+    };
+    let roots = media_roots(state);
+    match tokio::task::spawn_blocking(move || read_text_file(Path::new(&resolved), &roots)).await {
+        Ok(Ok(content)) => msg.text = content,
+        Ok(Err(e)) => eprintln!("[textFile] {}", e),
+        Err(e) => eprintln!("[textFile] Read task failed: {}", e),
+    }
+}
+
+/// Check every slideshow `folderPath` and message `textFile` reference on disk
+fn validate_media_references(state: &AppState) -> Vec<serde_json::Value> {
+    let mut refs: Vec<(String, String)> = Vec::new();
+
+    if let Ok(presets) = state.app_state_sync.visualization_presets.lock() {
+        for preset in presets.iter() {
+            if let Some(folder) = preset.settings.get("folderPath").and_then(|v| v.as_str()) {
+                if !folder.is_empty() {
+                    refs.push((format!("preset:{}", preset.id), folder.to_string()));
                 }
-                None
             }
-            _ => None,
         }
     }
-    
-    // Then, collect all message IDs from the folder
-    fn collect_ids(node: &serde_json::Value, ids: &mut Vec<String>) {
-        match node {
-            serde_json::Value::Array(arr) => {
-                for n in arr {
-                    collect_ids(n, ids);
-                }
-            }
-            serde_json::Value::Object(obj) => {
-                if let Some(t) = obj.get("type").and_then(|v| v.as_str()) {
-                    match t {
-                        "message" => {
-                            if let Some(msg) = obj.get("message") {
-                                if let Some(id) = msg.get("id").and_then(|v| v.as_str()) {
-                                    ids.push(id.to_string());
-                                }
-                            }
-                        }
-                        "folder" => {
-                            if let Some(children) = obj.get("children") {
-                                collect_ids(children, ids);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
+    if let Ok(messages) = state.app_state_sync.messages.lock() {
+        for msg in messages.iter() {
+            if let Some(file) = msg.text_file.as_deref().filter(|f| !f.is_empty()) {
+                refs.push((format!("message:{}", msg.id), file.to_string()));
             }
-            _ => {}
         }
     }
-    
-    let mut ids = Vec::new();
-    if let Some(folder) = find_folder(folder_id, tree) {
-        if let Some(children) = folder.get("children") {
-            collect_ids(children, &mut ids);
+
+    refs.into_iter()
+        .map(|(source, reference)| {
+            let resolved = resolve_media_path(state, &reference).ok();
+            let exists = resolved.as_deref().map(|p| Path::new(p).exists()).unwrap_or(false);
+            serde_json::json!({
+                "source": source,
+                "ref": reference,
+                "resolvedPath": resolved,
+                "exists": exists
+            })
+        })
+        .collect()
+}
+
+/// JSON body extractor that reports malformed bodies as
+/// `400 { error: { code: "bad-json", message } }` instead of axum's plain-text rejection.
+struct ApiJson<T>(T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection) => {
+                eprintln!("[Server] Rejected malformed JSON body: {}", rejection.body_text());
+                Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": {
+                            "code": "bad-json",
+                            "message": rejection.body_text()
+                        }
+                    })),
+                ).into_response())
+            }
         }
     }
-    ids
 }
 
+/// `{ error: { code, message } }` response with the given status
+fn error_response(status: StatusCode, code: &str, message: String) -> Response {
+    eprintln!("[Server] Command rejected ({}): {}", code, message);
+    (
+        status,
+        Json(serde_json::json!({
+            "error": {
+                "code": code,
+                "message": message
+            }
+        })),
+    ).into_response()
+}
+
+/// Reject a command payload with `400 { error: { code, message } }`
+fn command_error(code: &str, message: impl Into<String>) -> Response {
+    error_response(StatusCode::BAD_REQUEST, code, message.into())
+}
+
+/// Reject a command whose media path `allowed_media_file` refused: `403` outside the allowed
+/// media folders, `404` if it doesn't exist
+fn media_path_error(status: StatusCode, path: &str) -> Response {
+    if status == StatusCode::FORBIDDEN {
+        error_response(status, "forbidden-path", format!("{} is outside the allowed media folders", path))
+    } else {
+        error_response(status, "not-found", format!("Not found: {}", path))
+    }
+}
+
+/// Extracted palettes keyed by `path#count`, with the file mtime they were computed from
+type PaletteCache = HashMap<String, (SystemTime, Vec<String>)>;
+
 #[derive(Clone)]
 struct AppState {
     app_handle: AppHandle,
     app_state_sync: Arc<AppStateSync>,
     dist_path: std::path::PathBuf,
+    palette_cache: Arc<Mutex<PaletteCache>>,
+    exif_date_cache: Arc<Mutex<exif_date::ExifDateCache>>,
+    image_cache: Arc<Mutex<image_cache::ImageCache>>,
+    /// Flips to true when the app is shutting down; ends SSE streams and the server
+    shutdown_tx: tokio::sync::watch::Sender<bool>,
+}
+
+/// Remote `shutdown` is refused unless `VIBECAST_ALLOW_REMOTE_SHUTDOWN` is set
+fn remote_shutdown_allowed() -> bool {
+    std::env::var("VIBECAST_ALLOW_REMOTE_SHUTDOWN")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Destructive commands need a confirmation round-trip when `VIBECAST_CONFIRM_DESTRUCTIVE` is set
+fn confirm_destructive_enabled() -> bool {
+    std::env::var("VIBECAST_CONFIRM_DESTRUCTIVE")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Two-phase guard for destructive commands. Returns the response to send instead of running
+/// the command: a fresh `confirm-required` token on the first call, or an error for a bad token.
+/// `{ force: true }` skips the check; `{ confirmToken }` from the first call lets it through.
+fn require_confirmation(state: &AppState, payload: &RemoteCommand) -> Option<Response> {
+    if !confirm_destructive_enabled() {
+        return None;
+    }
+    let p = payload.payload.as_ref();
+    if p.and_then(|p| p.get("force")).and_then(|v| v.as_bool()).unwrap_or(false) {
+        return None;
+    }
+    match p.and_then(|p| p.get("confirmToken")).and_then(|v| v.as_str()) {
+        Some(token) if state.app_state_sync.consume_confirmation(&payload.command, token) => None,
+        Some(_) => Some(command_error(
+            "invalid-confirmation",
+            format!("Confirmation token for '{}' is unknown or expired", payload.command),
+        )),
+        None => {
+            let token = state.app_state_sync.issue_confirmation(&payload.command);
+            Some(Json(serde_json::json!({
+                "status": "confirm-required",
+                "token": token,
+                "expiresInMs": CONFIRM_TOKEN_TTL.as_millis() as u64,
+            })).into_response())
+        }
+    }
+}
+
+/// Give the ack response time to reach the client before the process exits
+const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+
+/// Command payloads larger than this are left out of the event log
+const MAX_LOGGED_PAYLOAD_BYTES: usize = 2048;
+
+/// Largest `/api/command` body the access log will buffer (axum's default JSON limit)
+const MAX_COMMAND_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Enter or leave fullscreen on the viz window. The windowed position/size is saved on
+/// entering fullscreen and restored on leaving it. `None` toggles. Returns the new state.
+pub fn set_viz_fullscreen(
+    app_handle: &AppHandle,
+    app_state_sync: &AppStateSync,
+    fullscreen: Option<bool>,
+) -> Result<bool, String> {
+    let window = app_handle.get_webview_window("viz").ok_or("Viz window not found")?;
+    let is_fullscreen = window.is_fullscreen().map_err(|e| e.to_string())?;
+    let fullscreen = fullscreen.unwrap_or(!is_fullscreen);
+    if fullscreen == is_fullscreen {
+        return Ok(fullscreen);
+    }
+
+    if fullscreen {
+        if let (Ok(pos), Ok(size)) = (window.outer_position(), window.inner_size()) {
+            if let Ok(mut g) = app_state_sync.viz_windowed_geometry.lock() {
+                *g = Some(WindowGeometry { x: pos.x, y: pos.y, width: size.width, height: size.height });
+            }
+        }
+        window.set_fullscreen(true).map_err(|e| e.to_string())?;
+    } else {
+        window.set_fullscreen(false).map_err(|e| e.to_string())?;
+        let saved = app_state_sync.viz_windowed_geometry.lock().ok().and_then(|mut g| g.take());
+        if let Some(g) = saved {
+            let _ = window.set_position(PhysicalPosition::new(g.x, g.y));
+            let _ = window.set_size(PhysicalSize::new(g.width, g.height));
+        }
+    }
+    Ok(fullscreen)
+}
+
+/// Capture what the viz window currently shows as PNG bytes, by screenshotting its bounds.
+/// Webviews have no capture API, so this relies on the platform screenshot tool (macOS only).
+pub fn capture_viz_screenshot(app_handle: &AppHandle) -> Result<Vec<u8>, String> {
+    let window = app_handle.get_webview_window("viz").ok_or("Viz window not found")?;
+    let pos = window.outer_position().map_err(|e| e.to_string())?;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let scale = window.scale_factor().map_err(|e| e.to_string())?;
+    capture_screen_region(
+        pos.to_logical::<f64>(scale).into(),
+        size.to_logical::<f64>(scale).into(),
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn capture_screen_region(pos: (f64, f64), size: (f64, f64)) -> Result<Vec<u8>, String> {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let tmp = std::env::temp_dir().join(format!("vibecast-screenshot-{}-{}.png", std::process::id(), nanos));
+    // `-R` takes the region in points, matching logical window coordinates
+    let region = format!("{},{},{},{}", pos.0.round(), pos.1.round(), size.0.round(), size.1.round());
+    let status = std::process::Command::new("screencapture")
+        .args(["-x", "-t", "png", "-R", &region])
+        .arg(&tmp)
+        .status()
+        .map_err(|e| format!("Failed to run screencapture: {}", e))?;
+    let bytes = std::fs::read(&tmp);
+    let _ = std::fs::remove_file(&tmp);
+    if !status.success() {
+        return Err(format!("screencapture failed ({}); is screen recording permission granted?", status));
+    }
+    bytes.map_err(|e| format!("Failed to read screenshot: {}", e))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn capture_screen_region(_pos: (f64, f64), _size: (f64, f64)) -> Result<Vec<u8>, String> {
+    Err("Screenshots are only supported on macOS".to_string())
+}
+
+/// Build the CORS layer. `VIBECAST_CORS_ORIGINS` (comma-separated, e.g.
+/// `http://192.168.1.10:5173,https://remote.example`) restricts cross-origin access to
+/// those origins; when unset, any origin is allowed.
+fn cors_layer() -> CorsLayer {
+    let Ok(raw) = std::env::var("VIBECAST_CORS_ORIGINS") else {
+        return CorsLayer::permissive();
+    };
+
+    let mut origins = Vec::new();
+    for origin in raw.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+        let origin = origin.trim_end_matches('/');
+        let valid_scheme = origin.starts_with("http://") || origin.starts_with("https://");
+        match origin.parse::<header::HeaderValue>() {
+            Ok(value) if valid_scheme => origins.push(value),
+            _ => eprintln!("[cors] Ignoring invalid origin in VIBECAST_CORS_ORIGINS: {}", origin),
+        }
+    }
+    if origins.is_empty() {
+        eprintln!("[cors] VIBECAST_CORS_ORIGINS has no valid origins; cross-origin requests will be rejected");
+    } else {
+        println!("[cors] Allowing origins: {:?}", origins);
+    }
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods(Any)
+        .allow_headers(Any)
 }
 
 pub async fn start_server(app_handle: AppHandle, app_state_sync: Arc<AppStateSync>, port: u16) {
@@ -190,9 +482,19 @@ pub async fn start_server(app_handle: AppHandle, app_state_sync: Arc<AppStateSyn
         app_handle: app_handle.clone(),
         app_state_sync: app_state_sync.clone(),
         dist_path: dist_path.clone(),
+        palette_cache: Arc::new(Mutex::new(HashMap::new())),
+        exif_date_cache: Arc::new(Mutex::new(HashMap::new())),
+        image_cache: Arc::new(Mutex::new(image_cache::ImageCache::from_env())),
+        shutdown_tx: tokio::sync::watch::channel(false).0,
     };
+    let mut server_shutdown_rx = state.shutdown_tx.subscribe();
     let app_state_sync = state.app_state_sync.clone();
 
+    // Idle auto-dim: fade the display down when no commands arrive for a while
+    tokio::spawn(auto_dim_loop(state.clone()));
+    tokio::spawn(common_settings_ramp_loop(state.clone()));
+    tokio::spawn(schedule_loop(state.clone()));
+
     // Log the dist path for debugging
     eprintln!("[Server] Serving static files from: {:?}", dist_path);
     eprintln!("[Server] Path exists: {}", dist_path.exists());
@@ -204,18 +506,45 @@ pub async fn start_server(app_handle: AppHandle, app_state_sync: Arc<AppStateSyn
     }
 
     let app = Router::new()
-        .route("/api/command", post(handle_command))
+        .route(
+            "/api/command",
+            post(handle_command).layer(middleware::from_fn_with_state(state.clone(), access_log_middleware)),
+        )
+        .route("/api/access-log", get(get_access_log))
         .route("/api/state", get(get_state))
         .route("/api/status", get(get_status))
+        .route("/api/version", get(get_version))
         .route("/api/events", get(state_events))
+        .route("/api/stream.ndjson", get(state_ndjson))
         .route("/api/e2e/report", post(handle_e2e_report))
         .route("/api/e2e/last-report", get(get_last_e2e_report))
         .route("/api/images/list", get(list_images))
         .route("/api/images/serve", get(serve_image))
+        .route("/api/images/zip", get(zip_images))
+        .route("/api/images/thumbnail", get(serve_thumbnail))
+        .route("/api/screenshot", get(get_screenshot))
+        .route("/api/config/export", get(export_config))
+        .route("/api/config/import", post(import_config))
+        .route("/api/stats/export.csv", get(export_stats_csv))
+        .route("/api/stats/histogram", get(get_stats_histogram))
+        .route("/api/validate-media", get(validate_media))
+        .route("/api/resolve-path", get(resolve_path_info))
+        .route("/api/slideshow/folders", get(get_slideshow_folders))
+        .route("/api/folders", get(get_folders))
+        .route("/api/text-styles", get(get_text_styles))
+        .route("/api/visualizations", get(get_visualizations))
+        .route("/api/export/tree.md", get(export_tree_markdown))
+        .route("/api/queue/next", get(get_queue_next))
+        .route("/api/folder/:id/messages", get(get_folder_messages))
+        .route("/api/log", get(get_event_log))
+        .route("/api/summary", get(get_summary))
+        .route("/api/logs", get(get_logs))
+        .route("/api/messages/count", get(get_message_count))
+        .route("/api/messages/recent", get(get_recent_messages))
         .route_service("/youtube_player.html", ServeFile::new(dist_path.join("youtube_player.html")))
         .nest_service("/assets", ServeDir::new(dist_path.join("assets")))
         .fallback(get(serve_spa))
-        .layer(CorsLayer::permissive())
+        .layer(cors_layer())
         .with_state(state);
 
     // Try a range of ports (helps when a previous instance is still running).
@@ -260,11 +589,168 @@ pub async fn start_server(app_handle: AppHandle, app_state_sync: Arc<AppStateSyn
     };
 
     println!("Server listening on http://{}", addr);
-    if let Err(err) = axum::serve(listener, app).await {
+    let serve = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            let _ = server_shutdown_rx.wait_for(|shutting_down| *shutting_down).await;
+        });
+    if let Err(err) = serve.await {
         eprintln!("LAN server exited: {}", err);
     }
 }
 
+/// Switch to the playing message's visualization override, or restore the visualization
+/// from before the override, and tell the visualizer if that changed anything
+fn apply_visualization_override(state: &AppState, message: Option<&MessageConfig>) {
+    if let Some(viz) = state.app_state_sync.apply_visualization_override(message) {
+        let command = RemoteCommand {
+            command: "set-active-visualization".to_string(),
+            payload: Some(serde_json::Value::String(viz)),
+        };
+        state.app_state_sync.broadcast_command(command.clone());
+        let _ = state.app_handle.emit("remote-command", &command);
+    }
+}
+
+/// Push the current visualization presets to clients that follow commands rather than state (the visualizer)
+fn emit_visualization_presets(state: &AppState) {
+    let presets = state.app_state_sync.visualization_presets.lock()
+        .map(|p| p.clone())
+        .unwrap_or_default();
+    let command = RemoteCommand {
+        command: "set-visualization-presets".to_string(),
+        payload: serde_json::to_value(presets).ok(),
+    };
+    state.app_state_sync.broadcast_command(command.clone());
+    let _ = state.app_handle.emit("remote-command", &command);
+}
+
+/// Set one YouTube setting without touching the rest of the settings blob. The active preset is
+/// patched too when it is a YouTube preset, since the visualizer uses its settings over
+/// `visualization_settings`.
+fn set_youtube_setting(state: &AppState, key: &str, value: serde_json::Value) {
+    let settings = state.app_state_sync.visualization_settings.lock()
+        .map(|mut m| {
+            merge_json_patch(&mut m, &serde_json::json!({ "youtube": { key: value.clone() } }));
+            m.clone()
+        })
+        .unwrap_or_default();
+    let command = RemoteCommand {
+        command: "set-visualization-settings".to_string(),
+        payload: Some(settings),
+    };
+    state.app_state_sync.broadcast_command(command.clone());
+    let _ = state.app_handle.emit("remote-command", &command);
+
+    let active = state.app_state_sync.active_visualization_preset.lock()
+        .ok()
+        .and_then(|p| p.clone());
+    let mut preset_changed = false;
+    if let (Some(active), Ok(mut presets)) = (active, state.app_state_sync.visualization_presets.lock()) {
+        if let Some(preset) = presets.iter_mut().find(|p| p.id == active && p.visualization_id == "youtube") {
+            merge_json_patch(&mut preset.settings, &serde_json::json!({ key: value }));
+            preset_changed = true;
+        }
+    }
+    if preset_changed {
+        emit_visualization_presets(state);
+    }
+}
+
+/// Push the current common settings to clients that follow commands rather than state (the visualizer)
+fn emit_common_settings(state: &AppState) {
+    let settings = state.app_state_sync.common_settings.lock()
+        .map(|m| m.clone())
+        .unwrap_or_default();
+    let command = RemoteCommand {
+        command: "set-common-settings".to_string(),
+        payload: serde_json::to_value(settings).ok(),
+    };
+    state.app_state_sync.broadcast_command(command.clone());
+    let _ = state.app_handle.emit("remote-command", &command);
+}
+
+async fn auto_dim_loop(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_millis(250));
+    loop {
+        interval.tick().await;
+        if state.app_state_sync.auto_dim_tick() {
+            emit_common_settings(&state);
+            state.app_state_sync.broadcast_state();
+        }
+    }
+}
+
+/// Push intermediate values while smoothed common settings ramp to their target
+async fn common_settings_ramp_loop(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_millis(33));
+    loop {
+        interval.tick().await;
+        if state.app_state_sync.common_settings_ramp_tick() {
+            emit_common_settings(&state);
+            state.app_state_sync.broadcast_state();
+        }
+    }
+}
+
+/// Reserved id of the transient message shown by `flash-message`
+const FLASH_MESSAGE_ID: &str = "__flash";
+
+/// Shortest `schedule-recurring` interval; matches the schedule loop's tick
+const MIN_RECURRING_INTERVAL_MS: u64 = 250;
+
+/// Fire scheduled messages when due, skipping (and logging) ones that expired while
+/// the app was asleep so a resume doesn't cause a stampede of overdue messages.
+/// Recurring schedules fire the message's current config from the message list.
+async fn schedule_loop(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_millis(250));
+    loop {
+        interval.tick().await;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let due = state.app_state_sync.take_due_scheduled(now);
+        let due_recurring = state.app_state_sync.take_due_recurring(now);
+        if due.is_empty() && due_recurring.is_empty() {
+            continue;
+        }
+        for scheduled in due {
+            if scheduled.is_expired(now) {
+                println!("[schedule] Skipping {}: overdue by {}ms", scheduled.id, now - scheduled.at);
+                state.app_state_sync.log_event("schedule-expired", Some(serde_json::json!({
+                    "id": scheduled.id,
+                    "at": scheduled.at,
+                    "overdueMs": now - scheduled.at
+                })));
+                continue;
+            }
+            println!("[schedule] Firing {}", scheduled.id);
+            let command = RemoteCommand {
+                command: "trigger-message".to_string(),
+                payload: Some(scheduled.payload),
+            };
+            replay_command(state.clone(), command).await;
+        }
+        for message_id in due_recurring {
+            let msg = state.app_state_sync.messages.lock()
+                .ok()
+                .and_then(|messages| messages.iter().find(|m| m.id == message_id).cloned());
+            let Some(msg) = msg else {
+                println!("[schedule] Skipping recurring message {}: no longer exists", message_id);
+                continue;
+            };
+            println!("[schedule] Firing recurring message {}", message_id);
+            let command = RemoteCommand {
+                command: "trigger-message".to_string(),
+                payload: serde_json::to_value(msg).ok(),
+            };
+            replay_command(state.clone(), command).await;
+        }
+        // Keep clients' view of pending schedules current, including skipped ones
+        state.app_state_sync.broadcast_state();
+    }
+}
+
 async fn list_images(
     State(state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
@@ -276,23 +762,12 @@ async fn list_images(
         return Json(vec![]);
     }
     
-    let resolved = if folder_path.starts_with("$RESOURCES/") {
-        let subpath = &folder_path["$RESOURCES/".len()..];
-        match state.app_handle.path().resolve(subpath, BaseDirectory::Resource) {
-            Ok(p) => {
-                eprintln!("[Server] Resolved resource '{}' to: {:?}", subpath, p);
-                p.to_string_lossy().to_string()
-            },
-            Err(e) => {
-                eprintln!("[Server] ERROR: Failed to resolve resource '{}': {}", subpath, e);
-                return Json(vec![]);
-            }
+    let resolved = match resolve_media_path(&state, &folder_path) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("[Server] ERROR: {}", e);
+            return Json(vec![]);
         }
-    } else {
-        let base_path_opt = state.app_state_sync.config_base_path.lock()
-            .ok()
-            .and_then(|p| p.clone());
-        resolve_path(&folder_path, base_path_opt.as_deref())
     };
     
     eprintln!("[Server] Final resolved path: {}", resolved);
@@ -303,38 +778,230 @@ async fn list_images(
         return Json(vec![]);
     }
     
-    let image_extensions = ["jpg", "jpeg", "png", "gif", "webp", "bmp", "tiff", "tif", "heic", "heif"];
-    let video_extensions = ["mp4", "mov", "webm", "m4v", "avi", "mkv"];
+    // The slideshow fetches these through /api/images/serve
+    state.app_state_sync.allow_media_root(path);
+
     let mut media_files = Vec::new();
-    
-    if let Ok(entries) = std::fs::read_dir(path) {
-        for entry in entries.flatten() {
-            let entry_path = entry.path();
-            if entry_path.is_file() {
-                if let Some(ext) = entry_path.extension() {
-                    let ext_str = ext.to_string_lossy().to_lowercase();
-                    if image_extensions.contains(&ext_str.as_str()) || video_extensions.contains(&ext_str.as_str()) {
-                        if let Some(path_str) = entry_path.to_str() {
-                            // Strip \\?\ prefix on Windows if present
-                            let clean_path = if cfg!(windows) && path_str.starts_with(r"\\?\") {
-                                &path_str[4..]
-                            } else {
-                                path_str
-                            };
-                            media_files.push(clean_path.to_string());
-                        }
-                    }
-                }
-            }
+    for entry_path in media_files_in(path) {
+        if let Some(path_str) = entry_path.to_str() {
+            // Strip \\?\ prefix on Windows if present
+            let clean_path = if cfg!(windows) && path_str.starts_with(r"\\?\") {
+                &path_str[4..]
+            } else {
+                path_str
+            };
+            media_files.push(clean_path.to_string());
         }
     }
     
     media_files.sort();
+    // `?sort=exif_date` orders by when photos were taken rather than by name
+    if params.get("sort").is_some_and(|s| s == "exif_date") {
+        let cache = state.exif_date_cache.clone();
+        let sorted = tokio::task::spawn_blocking(move || {
+            if let Ok(mut cache) = cache.lock() {
+                exif_date::sort_by_capture_date(&mut media_files, &mut cache);
+            }
+            media_files
+        }).await;
+        media_files = match sorted {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("[Server] ERROR: sorting by capture date failed: {}", e);
+                return Json(vec![]);
+            }
+        };
+    }
     eprintln!("[Server] Found {} media files", media_files.len());
     Json(media_files)
 }
 
+/// Image and video files directly inside `dir` (not recursive), unsorted
+fn media_files_in(dir: &Path) -> Vec<std::path::PathBuf> {
+    let image_extensions = ["jpg", "jpeg", "png", "gif", "webp", "bmp", "tiff", "tif", "heic", "heif"];
+    let video_extensions = ["mp4", "mov", "webm", "m4v", "avi", "mkv"];
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            p.extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .is_some_and(|ext| image_extensions.contains(&ext.as_str()) || video_extensions.contains(&ext.as_str()))
+        })
+        .collect()
+}
+
+/// Media files inside `dir` and all its subfolders, unsorted. Symlinked folders aren't
+/// followed, so the walk can't loop or leave `dir`.
+fn media_files_recursive(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut files = media_files_in(dir);
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            files.extend(media_files_recursive(&entry.path()));
+        }
+    }
+    files
+}
+
+/// Directory of the on-disk thumbnail cache
+fn thumbnail_cache_dir(state: &AppState) -> Result<std::path::PathBuf, String> {
+    state.app_handle.path().app_cache_dir()
+        .map(|dir| dir.join("thumbnails"))
+        .map_err(|e| format!("Failed to resolve cache dir: {}", e))
+}
+
+/// Generate disk-cached thumbnails for `files`, a few at a time, reporting
+/// progress as `thumbnail-progress` events (Tauri and SSE)
+async fn pregenerate_thumbnails(state: AppState, folder: String, files: Vec<std::path::PathBuf>, cache_dir: std::path::PathBuf, size: u32) {
+    let total = files.len();
+    let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2);
+    let mut results = futures::stream::iter(files)
+        .map(|file| {
+            let cache_dir = cache_dir.clone();
+            tokio::task::spawn_blocking(move || thumbnails::ensure_thumbnail(&cache_dir, &file, size))
+        })
+        .buffer_unordered(workers);
+
+    let (mut done, mut failed) = (0, 0);
+    while let Some(result) = results.next().await {
+        done += 1;
+        if let Err(e) = result.map_err(|e| e.to_string()).and_then(|r| r) {
+            failed += 1;
+            eprintln!("[Server] Thumbnail failed: {}", e);
+        }
+        let progress = RemoteCommand {
+            command: "thumbnail-progress".to_string(),
+            payload: Some(serde_json::json!({
+                "folder": folder,
+                "done": done,
+                "failed": failed,
+                "total": total,
+            })),
+        };
+        state.app_state_sync.broadcast_command(progress.clone());
+        let _ = state.app_handle.emit("thumbnail-progress", &progress.payload);
+    }
+    eprintln!("[Server] Pre-generated {} thumbnails for {} ({} failed)", total - failed, folder, failed);
+}
+
+/// Thumbnail (JPEG, longest edge `size`) of an image, generated on first request and then
+/// served from the disk cache
+async fn serve_thumbnail(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let Some(path_str) = params.get("path") else {
+        return (StatusCode::BAD_REQUEST, "Missing path parameter").into_response();
+    };
+    let size = thumbnails::clamp_size(params.get("size").and_then(|s| s.parse().ok()));
+    let (resolved, cache_dir) = match (resolve_media_path(&state, path_str), thumbnail_cache_dir(&state)) {
+        (Ok(r), Ok(c)) => (r, c),
+        (Err(e), _) | (_, Err(e)) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let file = match allowed_media_file(&state, &resolved) {
+        Ok(file) if file.is_file() => file,
+        Err(StatusCode::FORBIDDEN) => {
+            return (StatusCode::FORBIDDEN, "Path is outside allowed folders").into_response();
+        }
+        _ => return (StatusCode::NOT_FOUND, "File not found").into_response(),
+    };
+    let result = tokio::task::spawn_blocking(move || {
+        thumbnails::ensure_thumbnail(&cache_dir, &file, size)
+            .and_then(|p| std::fs::read(p).map_err(|e| e.to_string()))
+    }).await;
+    match result {
+        Ok(Ok(bytes)) => (
+            [
+                (header::CONTENT_TYPE, "image/jpeg"),
+                (header::CACHE_CONTROL, "max-age=3600"),
+            ],
+            bytes,
+        ).into_response(),
+        Ok(Err(e)) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// PNG of what the viz window currently shows
+async fn get_screenshot(State(state): State<AppState>) -> Response {
+    if state.app_handle.get_webview_window("viz").is_none() {
+        return (StatusCode::NOT_FOUND, "Viz window not found").into_response();
+    }
+    let app_handle = state.app_handle.clone();
+    match tokio::task::spawn_blocking(move || capture_viz_screenshot(&app_handle)).await {
+        Ok(Ok(bytes)) => (
+            [
+                (header::CONTENT_TYPE, "image/png"),
+                (header::CACHE_CONTROL, "no-store"),
+            ],
+            bytes,
+        ).into_response(),
+        Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Download the media of a folder (as listed by `list_images`) as a ZIP, streamed as it is
+/// written. `recursive=true` includes subfolders, keeping their paths in the archive.
+async fn zip_images(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let Some(folder_path) = params.get("folder").filter(|f| !f.is_empty()) else {
+        return (StatusCode::BAD_REQUEST, "Missing folder parameter").into_response();
+    };
+    let resolved = match resolve_media_path(&state, folder_path) {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let dir = match allowed_media_file(&state, &resolved) {
+        Ok(dir) if dir.is_dir() => dir,
+        Ok(_) | Err(StatusCode::NOT_FOUND) => return (StatusCode::NOT_FOUND, "Folder not found").into_response(),
+        Err(status) => return (status, "Folder is outside the allowed media folders").into_response(),
+    };
+
+    let recursive = params.get("recursive").is_some_and(|v| v == "true");
+    let mut files = if recursive { media_files_recursive(&dir) } else { media_files_in(&dir) };
+    // Symlinked files could point anywhere; only zip what really lies in the folder
+    let roots = [dir.clone()];
+    files.retain(|f| check_media_path(f, &roots).is_ok());
+    files.sort();
+    let archive_name = dir.file_name()
+        .map(|n| n.to_string_lossy().replace('"', ""))
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "media".to_string());
+    eprintln!("[Server] Zipping {} media files from {}", files.len(), resolved);
+
+    let (tx, rx) = tokio::sync::mpsc::channel(8);
+    let log_path = resolved.clone();
+    tokio::task::spawn_blocking(move || {
+        let writer = media_zip::ChannelWriter::new(tx.clone());
+        if let Err(e) = media_zip::write_media_zip(&dir, &files, writer) {
+            eprintln!("[Server] ZIP of {} failed: {}", log_path, e);
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.zip\"", archive_name)),
+        ],
+        Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)),
+    ).into_response()
+}
+
+/// Response header reporting whether `serve_image` was served from the memory cache
+const IMAGE_CACHE_HEADER: header::HeaderName = header::HeaderName::from_static("x-vibecast-cache");
+
 async fn serve_image(
+    State(state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Response {
     let path_str = match params.get("path") {
@@ -342,20 +1009,96 @@ async fn serve_image(
         None => return (StatusCode::BAD_REQUEST, "Missing path parameter").into_response(),
     };
     
-    // Basic validation/security check?
-    // Since this is a local app intended for "vibe coding", we'll be permissive,
-    // but in a real app we'd want to verify the path is within allowed directories.
+    let file_path = match allowed_media_file(&state, path_str) {
+        Ok(p) => p,
+        Err(StatusCode::FORBIDDEN) => {
+            eprintln!("[Server] Refusing to serve '{}': outside allowed folders", path_str);
+            return (StatusCode::FORBIDDEN, "Path is outside allowed folders").into_response();
+        }
+        Err(status) => return (status, "File not found").into_response(),
+    };
     
-    match tokio::fs::read(path_str).await {
-        Ok(bytes) => {
-            let mime_type = mime_guess::from_path(path_str).first_or_octet_stream();
-            ([(header::CONTENT_TYPE, mime_type.as_ref())], bytes).into_response()
+    // Optional `format=jpeg|png|webp` transcodes for displays that can't render the source
+    let format = match params.get("format").map(|f| f.to_ascii_lowercase()) {
+        None => None,
+        Some(f) => match f.as_str() {
+            "jpeg" | "jpg" => Some(image::ImageFormat::Jpeg),
+            "png" => Some(image::ImageFormat::Png),
+            "webp" => Some(image::ImageFormat::WebP),
+            _ => return (StatusCode::BAD_REQUEST, format!("Unsupported format: {}", f)).into_response(),
         },
+    };
+    if let Some(e) = format.and_then(|_| media_probe::heif_unsupported(&file_path)) {
+        return (StatusCode::UNSUPPORTED_MEDIA_TYPE, e).into_response();
+    }
+    let mime_type = match format {
+        Some(f) => f.to_mime_type().to_string(),
+        None => mime_guess::from_path(path_str).first_or_octet_stream().to_string(),
+    };
+    // Transcoded output is cached separately from the original bytes
+    let cache_key = match format {
+        Some(f) => format!("{}#{}", path_str, f.extensions_str()[0]),
+        None => path_str.clone(),
+    };
+    let mtime = tokio::fs::metadata(&file_path).await.and_then(|m| m.modified()).ok();
+
+    // Repeat requests for an unchanged file are served from memory
+    if let Some(mtime) = mtime {
+        let cached = state.image_cache.lock().ok().and_then(|mut c| c.get(&cache_key, mtime));
+        if let Some(bytes) = cached {
+            return (
+                [(header::CONTENT_TYPE, mime_type.as_str()), (IMAGE_CACHE_HEADER, "hit")],
+                bytes,
+            ).into_response();
+        }
+    }
+
+    let bytes = match tokio::fs::read(&file_path).await {
+        Ok(bytes) => bytes,
         Err(e) => {
             eprintln!("[Server] Failed to read file '{}': {}", path_str, e);
-            (StatusCode::NOT_FOUND, "File not found").into_response()
+            return (StatusCode::NOT_FOUND, "File not found").into_response();
+        }
+    };
+    let bytes = match format {
+        Some(format) => {
+            let transcoded = tokio::task::spawn_blocking(move || transcode_image(&bytes, format))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|r| r);
+            match transcoded {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    eprintln!("[Server] Failed to transcode '{}': {}", path_str, e);
+                    return (StatusCode::UNSUPPORTED_MEDIA_TYPE, e).into_response();
+                }
+            }
+        }
+        None => bytes,
+    };
+    let bytes = axum::body::Bytes::from(bytes);
+    if let Some(mtime) = mtime {
+        if let Ok(mut cache) = state.image_cache.lock() {
+            cache.insert(&cache_key, mtime, bytes.clone());
         }
     }
+    (
+        [(header::CONTENT_TYPE, mime_type.as_str()), (IMAGE_CACHE_HEADER, "miss")],
+        bytes,
+    ).into_response()
+}
+
+/// Re-encode an image in another format. JPEG has no alpha channel, so it is dropped.
+fn transcode_image(bytes: &[u8], format: image::ImageFormat) -> Result<Vec<u8>, String> {
+    let img = image::load_from_memory(bytes).map_err(|e| format!("Cannot decode image: {}", e))?;
+    let img = if format == image::ImageFormat::Jpeg {
+        image::DynamicImage::ImageRgb8(img.to_rgb8())
+    } else {
+        img
+    };
+    let mut out = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut out, format).map_err(|e| format!("Cannot encode image: {}", e))?;
+    Ok(out.into_inner())
 }
 
 async fn serve_spa(State(state): State<AppState>) -> impl IntoResponse {
@@ -384,11 +1127,23 @@ async fn serve_spa(State(state): State<AppState>) -> impl IntoResponse {
 
 async fn handle_command(
     State(state): State<AppState>,
-    Json(payload): Json<RemoteCommand>,
-) -> Json<serde_json::Value> {
+    ApiJson(payload): ApiJson<RemoteCommand>,
+) -> Response {
     println!("Received command: {}", payload.command);
+
+    // Any command counts as activity and wakes the display from idle auto-dim
+    if state.app_state_sync.touch_activity() {
+        emit_common_settings(&state);
+    }
+    state.app_state_sync.record_command(&payload);
+    // Large payloads (trees, config imports) are logged by name only
+    let log_detail = payload.payload.clone()
+        .filter(|p| p.to_string().len() <= MAX_LOGGED_PAYLOAD_BYTES);
+    state.app_state_sync.log_event(&payload.command, log_detail);
     
     let mut triggered_message: Option<MessageConfig> = None;
+    // Commands that report data back add it to the response
+    let mut response = serde_json::json!({ "status": "ok" });
     
     // Update the canonical state based on command
     match payload.command.as_str() {
@@ -406,6 +1161,10 @@ async fn handle_command(
                 if let Ok(mut m) = state.app_state_sync.active_visualization.lock() {
                     *m = viz.to_string();
                 }
+                // An explicit choice wins over restoring the pre-override visualization
+                if let Ok(mut before) = state.app_state_sync.visualization_before_override.lock() {
+                    *before = None;
+                }
             }
         }
         "set-enabled-visualizations" => {
@@ -417,15 +1176,44 @@ async fn handle_command(
                 }
             }
         }
+        "reorder-enabled-visualization" => {
+            // { id, targetIndex }: move an enabled visualization within the mode-switcher order
+            let p = payload.payload.as_ref();
+            let Some(id) = p.and_then(|p| p.get("id")).and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing id");
+            };
+            let Some(target_index) = p.and_then(|p| p.get("targetIndex")).and_then(|v| v.as_u64()) else {
+                return command_error("invalid-payload", "Missing targetIndex");
+            };
+            if let Ok(mut m) = state.app_state_sync.enabled_visualizations.lock() {
+                if let Some(from) = m.iter().position(|v| v == id) {
+                    let viz = m.remove(from);
+                    let to = (target_index as usize).min(m.len());
+                    m.insert(to, viz);
+                }
+            }
+        }
         "set-common-settings" => {
             if let Some(p) = &payload.payload {
                 if let Ok(settings) = serde_json::from_value::<CommonSettings>(p.clone()) {
-                    if let Ok(mut m) = state.app_state_sync.common_settings.lock() {
-                        *m = settings;
+                    if state.app_state_sync.set_common_settings(settings) {
+                        // Ramping: the ramp loop pushes the values, so don't forward the
+                        // target to the visualizer now
+                        state.app_state_sync.broadcast_state();
+                        return Json(response).into_response();
                     }
                 }
             }
         }
+        "set-smooth-common-settings" => {
+            // `true`/`false` or { enabled }
+            let enabled = payload.payload.as_ref()
+                .and_then(|p| p.as_bool().or_else(|| p.get("enabled").and_then(|v| v.as_bool())));
+            let Some(enabled) = enabled else {
+                return command_error("invalid-payload", "Expected a boolean or { enabled }");
+            };
+            state.app_state_sync.smooth_common_settings.store(enabled, std::sync::atomic::Ordering::Relaxed);
+        }
         "set-visualization-settings" => {
             if let Some(p) = &payload.payload {
                 if let Ok(mut m) = state.app_state_sync.visualization_settings.lock() {
@@ -436,6 +1224,12 @@ async fn handle_command(
         // Message commands
         "trigger-message" => {
             if let Some(p) = &payload.payload {
+                // Optional output window routing; the payload is emitted unchanged so the
+                // frontend sees the same `target`.
+                let target = p.get("target").and_then(|v| v.as_str()).unwrap_or(TRIGGER_TARGET_ALL);
+                if !is_valid_trigger_target(target) {
+                    return command_error("invalid-target", format!("Unknown trigger target: {}", target));
+                }
                 // Handle both legacy (string) and new (MessageConfig) formats
                 let msg = if let Some(text) = p.as_str() {
                     // Legacy format - create a MessageConfig
@@ -450,65 +1244,151 @@ async fn handle_command(
                         speed: None,
                         split_enabled: None,
                         split_separator: None,
+                        background_color: None,
+                        overlay_opacity: None,
+                        visualization_override: None,
                     })
                 } else {
-                    serde_json::from_value::<MessageConfig>(p.clone()).ok()
+                    let mut p = p.clone();
+                    // Messages without their own style inherit the nearest folder default,
+                    // falling back to the global default text style.
+                    let missing_style = p.get("textStyle")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.is_empty())
+                        .unwrap_or(true);
+                    if missing_style {
+                        let folder_style = p.get("id")
+                            .and_then(|v| v.as_str())
+                            .and_then(|id| {
+                                state.app_state_sync.message_tree.lock()
+                                    .ok()
+                                    .and_then(|tree| inherited_text_style(&tree, id))
+                            });
+                        let style = folder_style.or_else(|| {
+                            state.app_state_sync.default_text_style.lock().ok().map(|s| s.clone())
+                        });
+                        if let (Some(style), Some(obj)) = (style, p.as_object_mut()) {
+                            obj.insert("textStyle".to_string(), serde_json::Value::String(style));
+                        }
+                    }
+                    serde_json::from_value::<MessageConfig>(p).ok()
                 };
+
+                if let Some(Err(e)) = msg.as_ref().map(|m| m.validate_display_overrides()) {
+                    return command_error("invalid-message", e);
+                }
                 
                 if let Some(msg) = msg {
-                    triggered_message = Some(msg.clone());
-                    
-                    // Update message stats
-                    if let Ok(mut stats) = state.app_state_sync.message_stats.lock() {
-                        let timestamp = std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap()
-                            .as_millis() as u64;
-                        
-                        let current_stats: serde_json::Value = stats.get(&msg.id)
-                            .cloned()
-                            .unwrap_or_else(|| serde_json::json!({
-                                "messageId": msg.id,
-                                "triggerCount": 0,
-                                "lastTriggered": 0,
-                                "history": []
-                            }));
-                        
-                        let trigger_count = current_stats.get("triggerCount")
-                            .and_then(|v| v.as_u64())
-                            .unwrap_or(0) + 1;
-                        
-                        let mut history = current_stats.get("history")
-                            .and_then(|v| v.as_array())
-                            .cloned()
-                            .unwrap_or_default();
-                        
-                        history.push(serde_json::json!({ "timestamp": timestamp }));
-                        // Keep last 50 entries
-                        if history.len() > 50 {
-                            history = history.into_iter().rev().take(50).rev().collect();
-                        }
-                        
-                        let new_stats = serde_json::json!({
-                            "messageId": msg.id,
-                            "triggerCount": trigger_count,
-                            "lastTriggered": timestamp,
-                            "history": history
-                        });
-                        
-                        if let Some(obj) = stats.as_object_mut() {
-                            obj.insert(msg.id.clone(), new_stats);
-                        } else {
-                            *stats = serde_json::json!({ msg.id: new_stats });
-                        }
+                    // `queue: true` waits behind the active message instead of interrupting it
+                    let queue = p.get("queue").and_then(|v| v.as_bool()).unwrap_or(false);
+                    if queue && state.app_state_sync.enqueue_message(msg.clone()) {
+                        println!("[trigger-message] Queued message {}", msg.id);
+                        response["queued"] = serde_json::json!(true);
+                        state.app_state_sync.broadcast_state();
+                        return Json(response).into_response();
                     }
+
+                    triggered_message = Some(msg.clone());
+                    state.app_state_sync.set_triggered_target(target);
+                    state.app_state_sync.set_active_message(Some(&msg.id));
+                    state.app_state_sync.record_recent_trigger(&msg.id);
+                    apply_visualization_override(&state, Some(&msg));
+                    record_trigger_stats(&state, &msg.id);
                 }
             }
         }
+        "flash-message" => {
+            // { text, textStyle?, durationMs? } - show throwaway text without storing a message
+            // or recording stats; cleared after `durationMs` if given
+            let Some(p) = payload.payload.as_ref().filter(|p| p.is_object()) else {
+                return command_error("invalid-payload", "flash-message requires { text, textStyle?, durationMs? }");
+            };
+            let Some(text) = p.get("text").and_then(|v| v.as_str()).filter(|t| !t.trim().is_empty()) else {
+                return command_error("invalid-payload", "flash-message requires non-empty text");
+            };
+            let duration_ms = match p.get("durationMs") {
+                None => None,
+                Some(v) => match v.as_u64().filter(|ms| *ms > 0) {
+                    Some(ms) => Some(ms),
+                    None => return command_error("invalid-payload", "durationMs must be a positive integer"),
+                },
+            };
+            let text_style = p.get("textStyle")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .or_else(|| state.app_state_sync.default_text_style.lock().ok().map(|s| s.clone()))
+                .unwrap_or_default();
+            let msg = MessageConfig {
+                id: FLASH_MESSAGE_ID.to_string(),
+                text: text.to_string(),
+                text_file: None,
+                text_style,
+                text_style_preset: None,
+                style_overrides: None,
+                repeat_count: None,
+                speed: None,
+                split_enabled: None,
+                split_separator: None,
+                background_color: None,
+                overlay_opacity: None,
+                visualization_override: None,
+            };
+
+            apply_visualization_override(&state, Some(&msg));
+            if let Some(ms) = duration_ms {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(ms)).await;
+                    // Cleared directly rather than replayed, so it stays out of the command log
+                    // and macro recordings
+                    if state.app_state_sync.clear_flash(FLASH_MESSAGE_ID) {
+                        apply_visualization_override(&state, None);
+                        let clear = RemoteCommand { command: "clear-message".to_string(), payload: None };
+                        state.app_state_sync.broadcast_command(clear.clone());
+                        let _ = state.app_handle.emit("remote-command", &clear);
+                    }
+                });
+            }
+
+            // Displays go through the normal trigger path, so send it as a trigger-message
+            let trigger = RemoteCommand {
+                command: "trigger-message".to_string(),
+                payload: serde_json::to_value(&msg).ok(),
+            };
+            state.app_state_sync.show_flash(msg);
+            state.app_state_sync.broadcast_command(trigger.clone());
+            let _ = state.app_handle.emit("remote-command", &trigger);
+            return Json(response).into_response();
+        }
+        "update-active-message-text" => {
+            // Accepts either "text" or { text: "..." }
+            let text = payload.payload.as_ref()
+                .and_then(|p| p.as_str().or_else(|| p.get("text").and_then(|v| v.as_str())));
+            let Some(text) = text else {
+                return command_error("invalid-payload", "update-active-message-text requires { text }");
+            };
+            let Some(msg) = state.app_state_sync.update_triggered_text(text) else {
+                return command_error("no-active-message", "No message is being displayed");
+            };
+            // Skip the normal tail: broadcasting `triggered_message` would restamp it as a new trigger
+            let live = RemoteCommand {
+                command: "update-active-message-text".to_string(),
+                payload: Some(serde_json::json!({ "messageId": msg.id, "text": msg.text, "patch": true })),
+            };
+            state.app_state_sync.broadcast_state();
+            state.app_state_sync.broadcast_command(live.clone());
+            let _ = state.app_handle.emit("remote-command", &live);
+            response["message"] = serde_json::json!(msg);
+            return Json(response).into_response();
+        }
         "set-messages" => {
             if let Some(p) = &payload.payload {
                 // Handle both legacy (string array) and new (MessageConfig array) formats
                 if let Ok(messages) = serde_json::from_value::<Vec<MessageConfig>>(p.clone()) {
+                    if let Err(e) = messages.iter().try_for_each(|m| m.validate_display_overrides()) {
+                        return command_error("invalid-message", e);
+                    }
                     if let Ok(mut m) = state.app_state_sync.messages.lock() {
                         *m = messages;
                     }
@@ -534,6 +1414,9 @@ async fn handle_command(
                                 speed: None,
                                 split_enabled: None,
                                 split_separator: None,
+                                background_color: None,
+                                overlay_opacity: None,
+                                visualization_override: None,
                             })
                         })
                         .collect();
@@ -551,16 +1434,157 @@ async fn handle_command(
         }
         "set-message-tree" => {
             if let Some(p) = &payload.payload {
+                // Also update the flattened messages list for backward compatibility / remote UI.
+                let flat = flatten_message_tree_value(p);
+                if let Err(e) = flat.iter().try_for_each(|m| m.validate_display_overrides()) {
+                    return command_error("invalid-message", e);
+                }
                 if let Ok(mut t) = state.app_state_sync.message_tree.lock() {
                     *t = p.clone();
                 }
-                // Also update the flattened messages list for backward compatibility / remote UI.
-                let flat = flatten_message_tree(p);
                 if let Ok(mut m) = state.app_state_sync.messages.lock() {
                     *m = flat;
                 }
             }
         }
+        "repair-message-tree" => {
+            // Reconcile the tree with the flat list, then regenerate the flat list from the tree
+            // Same lock order as config loading (messages, then tree)
+            if let (Ok(mut messages), Ok(mut tree)) = (
+                state.app_state_sync.messages.lock(),
+                state.app_state_sync.message_tree.lock(),
+            ) {
+                let report = repair_message_tree(&mut tree, &messages);
+                let before = messages.len();
+                *messages = flatten_message_tree_value(&tree);
+                response["report"] = serde_json::json!({
+                    "clean": report.is_clean(),
+                    "removedNodes": report.removed_nodes,
+                    "duplicateIds": report.duplicate_ids,
+                    "addedToTree": report.added_to_tree,
+                    "messagesBefore": before,
+                    "messagesAfter": messages.len(),
+                });
+            }
+        }
+        "delete-folder-messages" => {
+            // Removes every message under the folder (including subfolders); the folders stay
+            let Some(folder_id) = payload.payload.as_ref()
+                .and_then(|p| p.get("folderId"))
+                .and_then(|v| v.as_str())
+            else {
+                return command_error("invalid-payload", "delete-folder-messages requires { folderId }");
+            };
+            if let Some(r) = require_confirmation(&state, &payload) {
+                return r;
+            }
+            let mut deleted = 0;
+            if let (Ok(mut messages), Ok(mut tree)) = (
+                state.app_state_sync.messages.lock(),
+                state.app_state_sync.message_tree.lock(),
+            ) {
+                // From the raw tree, so messages without any style are deleted too
+                let Some(ids) = folder_message_ids(&tree, folder_id) else {
+                    return command_error("not-found", format!("Unknown folder '{}'", folder_id));
+                };
+                let ids: HashSet<String> = ids.into_iter().collect();
+                deleted = remove_tree_messages(&mut tree, &ids);
+                messages.retain(|m| !ids.contains(&m.id));
+                if let Ok(mut stats) = state.app_state_sync.message_stats.lock() {
+                    if let Some(obj) = stats.as_object_mut() {
+                        obj.retain(|id, _| !ids.contains(id));
+                    }
+                }
+            }
+            response["deleted"] = serde_json::json!(deleted);
+        }
+        "save-snapshot" => {
+            // { name } - keep the current state for later comparison with diff-snapshots
+            let Some(name) = payload.payload.as_ref()
+                .and_then(|p| p.as_str().or_else(|| p.get("name").and_then(|v| v.as_str())))
+                .filter(|n| !n.is_empty()) else {
+                return command_error("invalid-payload", "save-snapshot requires { name }");
+            };
+            state.app_state_sync.save_snapshot(name);
+            response["name"] = serde_json::json!(name);
+        }
+        "diff-snapshots" => {
+            // { a, b } -> { diff: { changedFields, messages, visualizationPresets, textStylePresets } }
+            let name = |key: &str| payload.payload.as_ref().and_then(|p| p.get(key)).and_then(|v| v.as_str());
+            let (Some(a), Some(b)) = (name("a"), name("b")) else {
+                return command_error("invalid-payload", "diff-snapshots requires { a, b }");
+            };
+            match state.app_state_sync.diff_snapshots(a, b) {
+                Ok(diff) => response["diff"] = diff,
+                Err(e) => return command_error("unknown-snapshot", e),
+            }
+        }
+        "search-messages" => {
+            // { query, caseSensitive? } -> { matches: [{ id, folderPath }] }, in tree order.
+            // Substring match on the text, and on the current `textFile` contents if any.
+            let Some(p) = &payload.payload else {
+                return command_error("invalid-payload", "search-messages requires { query }");
+            };
+            let Some(query) = p.get("query").and_then(|v| v.as_str()).filter(|q| !q.is_empty()) else {
+                return command_error("invalid-payload", "search-messages requires a non-empty query");
+            };
+            let case_sensitive = p.get("caseSensitive").and_then(|v| v.as_bool()).unwrap_or(false);
+
+            // Read textFile contents off the async runtime before matching
+            let file_backed: Vec<(String, String)> = state.app_state_sync.messages.lock()
+                .map(|m| m.iter().filter_map(|msg| Some((msg.id.clone(), text_file_path(&state, msg)?))).collect())
+                .unwrap_or_default();
+            let roots = media_roots(&state);
+            let file_texts: HashMap<String, String> = tokio::task::spawn_blocking(move || {
+                file_backed.into_iter()
+                    .filter_map(|(id, path)| Some((id, read_text_file(Path::new(&path), &roots).ok()?)))
+                    .collect()
+            })
+            .await
+            .unwrap_or_default();
+
+            let matches: Vec<serde_json::Value> = state.app_state_sync
+                .search_messages(query, case_sensitive, &file_texts)
+                .into_iter()
+                .map(|(id, folder_path)| serde_json::json!({ "id": id, "folderPath": folder_path }))
+                .collect();
+            response["matches"] = serde_json::json!(matches);
+        }
+        "reorder-message" => {
+            // { messageId, targetFolderId?, targetIndex } - targetFolderId null/absent is the top level
+            let p = payload.payload.as_ref();
+            let (Some(message_id), Some(target_index)) = (
+                p.and_then(|p| p.get("messageId")).and_then(|v| v.as_str()),
+                p.and_then(|p| p.get("targetIndex")).and_then(|v| v.as_u64()),
+            ) else {
+                return command_error("invalid-payload", "reorder-message requires { messageId, targetIndex }");
+            };
+            let target_folder_id = p.and_then(|p| p.get("targetFolderId")).and_then(|v| v.as_str());
+            if let (Ok(mut messages), Ok(mut tree)) = (
+                state.app_state_sync.messages.lock(),
+                state.app_state_sync.message_tree.lock(),
+            ) {
+                if let Err(e) = move_tree_message(&mut tree, message_id, target_folder_id, target_index as usize) {
+                    return command_error("invalid-move", e);
+                }
+                *messages = flatten_message_tree_value(&tree);
+            }
+        }
+        "validate-split" => {
+            // { text, separator, mode? } -> the parts the message would be split into; no state change
+            let p = payload.payload.as_ref();
+            let (Some(text), Some(separator)) = (
+                p.and_then(|p| p.get("text")).and_then(|v| v.as_str()),
+                p.and_then(|p| p.get("separator")).and_then(|v| v.as_str()),
+            ) else {
+                return command_error("invalid-payload", "validate-split requires { text, separator }");
+            };
+            let mode = p.and_then(|p| p.get("mode")).and_then(|v| v.as_str());
+            match split_message_text(text, separator, mode) {
+                Ok(segments) => response["segments"] = serde_json::json!(segments),
+                Err(e) => return command_error("invalid-split", e),
+            }
+        }
         "set-default-text-style" => {
             if let Some(style) = payload.payload.as_ref().and_then(|p| p.as_str()) {
                 if let Ok(mut m) = state.app_state_sync.default_text_style.lock() {
@@ -575,6 +1599,37 @@ async fn handle_command(
                 }
             }
         }
+        "set-text-audio-coupling" => {
+            // { enabled?, band?: "low" | "mid" | "high", amount?: 0..1 } - fields not given keep
+            // their current value. Stored in text_style_settings for the visualizer to apply.
+            let Some(p) = payload.payload.as_ref().filter(|p| p.is_object()) else {
+                return command_error("invalid-payload", "set-text-audio-coupling requires { enabled?, band?, amount? }");
+            };
+            let current = state.app_state_sync.text_style_settings.lock()
+                .ok()
+                .and_then(|s| s.get(TEXT_AUDIO_COUPLING_KEY).cloned())
+                .and_then(|c| serde_json::from_value::<TextAudioCoupling>(c).ok())
+                .unwrap_or_default();
+            let mut merged = serde_json::to_value(&current).unwrap_or_default();
+            merge_json_patch(&mut merged, p);
+            let coupling = match serde_json::from_value::<TextAudioCoupling>(merged) {
+                Ok(coupling) => coupling,
+                Err(e) => return command_error("invalid-payload", e.to_string()),
+            };
+            if let Err(e) = coupling.validate() {
+                return command_error("invalid-coupling", e);
+            }
+            let value = serde_json::to_value(&coupling).unwrap_or_default();
+            if let Ok(mut m) = state.app_state_sync.text_style_settings.lock() {
+                if !m.is_object() {
+                    *m = serde_json::json!({});
+                }
+                if let Some(obj) = m.as_object_mut() {
+                    obj.insert(TEXT_AUDIO_COUPLING_KEY.to_string(), value.clone());
+                }
+            }
+            response["coupling"] = value;
+        }
         "set-visualization-presets" => {
             if let Some(p) = &payload.payload {
                 if let Ok(presets) = serde_json::from_value::<Vec<VisualizationPreset>>(p.clone()) {
@@ -584,6 +1639,49 @@ async fn handle_command(
                 }
             }
         }
+        "clone-preset" => {
+            // { presetId, name, settingsOverride? } -> { preset }; the override is deep-merged
+            let Some(p) = &payload.payload else {
+                return command_error("invalid-payload", "Missing payload");
+            };
+            let Some(preset_id) = p.get("presetId").and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing presetId");
+            };
+            let Some(name) = p.get("name").and_then(|v| v.as_str()).map(str::trim).filter(|n| !n.is_empty()) else {
+                return command_error("invalid-payload", "Missing name");
+            };
+            let settings_override = p.get("settingsOverride").filter(|v| !v.is_null());
+            if settings_override.is_some_and(|o| !o.is_object()) {
+                return command_error("invalid-payload", "settingsOverride must be an object");
+            }
+
+            let cloned = state.app_state_sync.visualization_presets.lock()
+                .map_err(|_| "Presets unavailable".to_string())
+                .and_then(|mut presets| {
+                    let source = presets.iter()
+                        .find(|preset| preset.id == preset_id)
+                        .ok_or_else(|| format!("Preset not found: {}", preset_id))?;
+                    let mut preset = source.clone();
+                    preset.id = (1..)
+                        .map(|n| format!("{}-copy-{}", preset_id, n))
+                        .find(|id| !presets.iter().any(|p| &p.id == id))
+                        .unwrap_or_default();
+                    preset.name = name.to_string();
+                    if let Some(settings_override) = settings_override {
+                        merge_json_patch(&mut preset.settings, settings_override);
+                    }
+                    presets.push(preset.clone());
+                    Ok(preset)
+                });
+            match cloned {
+                Ok(preset) => {
+                    println!("[clone-preset] Cloned {} as {}", preset_id, preset.id);
+                    response["preset"] = serde_json::json!(preset);
+                }
+                Err(e) => return command_error("not-found", e),
+            }
+            emit_visualization_presets(&state);
+        }
         "set-active-visualization-preset" => {
             if let Some(p) = &payload.payload {
                 if p.is_null() {
@@ -614,6 +1712,10 @@ async fn handle_command(
                 }
             }
         }
+        "clear-message" => {
+            state.app_state_sync.set_active_message(None);
+            apply_visualization_override(&state, None);
+        }
         "clear-active-message" => {
             // Manual stop of a message - clear triggered message and handle queue
             if let Some(p) = &payload.payload {
@@ -634,6 +1736,9 @@ async fn handle_command(
                                         if let Some(next_id) = q.message_ids.get(q.current_index) {
                                             if let Ok(messages) = state.app_state_sync.messages.lock() {
                                                 next_message = messages.iter().find(|m| &m.id == next_id).cloned();
+                                                if let Some(msg) = next_message.as_mut() {
+                                                    q.apply_speed(msg);
+                                                }
                                             }
                                         }
                                     } else {
@@ -650,9 +1755,17 @@ async fn handle_command(
                             *queue = None;
                         }
                     }
+
+                    // Queued triggers play once any folder queue is done
+                    if next_message.is_none() {
+                        next_message = next_queued_message(&state, message_id);
+                    }
                     
+                    apply_visualization_override(&state, next_message.as_ref());
+
                     // Trigger next message if any
                     if let Some(msg) = next_message {
+                        state.app_state_sync.set_active_message(Some(&msg.id));
                         triggered_message = Some(msg.clone());
                         let trigger_cmd = serde_json::json!({
                             "command": "trigger-message",
@@ -676,25 +1789,25 @@ async fn handle_command(
                     // Check if we have a folder queue and this message is the current one
                     if let Ok(mut queue) = state.app_state_sync.folder_playback_queue.lock() {
                         if let Some(ref mut q) = *queue {
-                            if let Some(current_id) = q.message_ids.get(q.current_index) {
-                                if current_id == message_id {
-                                    println!("[message-complete] Advancing queue from index {} to {}", q.current_index, q.current_index + 1);
-                                    q.current_index += 1;
-                                    
-                                    if q.current_index < q.message_ids.len() {
-                                        // Get next message
-                                        if let Some(next_id) = q.message_ids.get(q.current_index) {
-                                            println!("[message-complete] Next message ID: {}", next_id);
-                                            if let Ok(messages) = state.app_state_sync.messages.lock() {
-                                                next_message = messages.iter().find(|m| &m.id == next_id).cloned();
-                                            }
+                            match q.advance(message_id) {
+                                QueueAdvance::Ignored => {}
+                                QueueAdvance::Held => {
+                                    // Hold the queue position until resume-folder-playback
+                                    println!("[message-complete] Queue paused, holding at index {}", q.current_index);
+                                }
+                                QueueAdvance::Next(next_id) => {
+                                    println!("[message-complete] Advancing queue to index {}, message ID: {}", q.current_index, next_id);
+                                    if let Ok(messages) = state.app_state_sync.messages.lock() {
+                                        next_message = messages.iter().find(|m| m.id == next_id).cloned();
+                                        if let Some(msg) = next_message.as_mut() {
+                                            q.apply_speed(msg);
                                         }
-                                    } else {
-                                        // Queue complete
-                                        println!("[message-complete] Queue complete");
-                                        should_clear_queue = true;
                                     }
                                 }
+                                QueueAdvance::Finished => {
+                                    println!("[message-complete] Queue complete");
+                                    should_clear_queue = true;
+                                }
                             }
                         }
                     }
@@ -704,10 +1817,19 @@ async fn handle_command(
                             *queue = None;
                         }
                     }
+
+                    // Queued triggers play once any folder queue is done
+                    if next_message.is_none() {
+                        next_message = next_queued_message(&state, message_id);
+                    }
                     
+                    apply_visualization_override(&state, next_message.as_ref());
+
                     // Trigger next message if any
-                    if let Some(msg) = next_message {
+                    if let Some(mut msg) = next_message {
+                        refresh_text_file(&state, &mut msg).await;
                         println!("[message-complete] Triggering next message: {}", msg.text);
+                        state.app_state_sync.set_active_message(Some(&msg.id));
                         triggered_message = Some(msg.clone());
                         
                         // Emit trigger-message to Tauri windows
@@ -720,45 +1842,345 @@ async fn handle_command(
                 }
             }
         }
-        "play-folder" => {
-            if let Some(p) = &payload.payload {
-                if let Some(folder_id) = p.get("folderId").and_then(|v| v.as_str()) {
-                    // Get message tree and collect message IDs from the folder
-                    let message_ids = if let Ok(tree) = state.app_state_sync.message_tree.lock() {
-                        collect_messages_from_folder(folder_id, &tree)
-                    } else {
-                        vec![]
-                    };
-                    
-                    if !message_ids.is_empty() {
-                        // Set up the queue
-                        if let Ok(mut queue) = state.app_state_sync.folder_playback_queue.lock() {
-                            *queue = Some(FolderPlaybackQueue {
-                                folder_id: folder_id.to_string(),
-                                message_ids: message_ids.clone(),
-                                current_index: 0,
-                            });
-                        }
-                        
-                        // Trigger the first message
-                        if let Some(first_id) = message_ids.first() {
-                            if let Ok(messages) = state.app_state_sync.messages.lock() {
-                                if let Some(msg) = messages.iter().find(|m| &m.id == first_id) {
-                                    let msg_clone = msg.clone();
-                                    triggered_message = Some(msg_clone.clone());
-                                    
-                                    // Emit trigger-message remote command to Tauri windows
-                                    // This ensures VisualizerWindow receives the command and actually plays the message
-                                    let trigger_cmd = serde_json::json!({
-                                        "command": "trigger-message",
-                                        "payload": msg_clone
-                                    });
-                                    let _ = state.app_handle.emit("remote-command", trigger_cmd);
-                                }
+        "play-folder" | "preview-folder" => {
+            // { folderId, shuffle?, loopPlayback? }
+            let preview = payload.command == "preview-folder";
+            let flag = |key: &str| payload.payload.as_ref()
+                .and_then(|p| p.get(key))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let (shuffle, loop_playback) = (flag("shuffle"), flag("loopPlayback"));
+            if let Some(folder_id) = payload.payload.as_ref().and_then(|p| p.get("folderId")).and_then(|v| v.as_str()) {
+                if let Some(first) = start_folder_queue(&state, folder_id, preview, shuffle, loop_playback) {
+                    triggered_message = Some(first);
+                }
+            }
+        }
+        "set-queue-speed" => {
+            // { queueId?: string, speed: number } - queueId is the queue's folder id
+            let Some(p) = &payload.payload else {
+                return command_error("invalid-payload", "Missing payload");
+            };
+            let Some(speed) = p.get("speed").and_then(|v| v.as_f64()).filter(|s| s.is_finite()) else {
+                return command_error("invalid-payload", "Missing speed");
+            };
+            let speed = speed.clamp(QUEUE_SPEED_MIN, QUEUE_SPEED_MAX);
+            let queue_id = p.get("queueId").and_then(|v| v.as_str());
+
+            let result = state.app_state_sync.folder_playback_queue.lock()
+                .map_err(|_| "Queue unavailable".to_string())
+                .and_then(|mut queue| match queue.as_mut() {
+                    None => Err("No folder queue is playing".to_string()),
+                    Some(q) if queue_id.is_some_and(|id| id != q.folder_id) => {
+                        Err(format!("Queue not playing: {}", queue_id.unwrap_or_default()))
+                    }
+                    Some(q) => {
+                        q.speed_multiplier = Some(speed);
+                        Ok(())
+                    }
+                });
+            if let Err(e) = result {
+                return command_error("no-queue", e);
+            }
+            println!("[set-queue-speed] Queue speed x{}", speed);
+            response["speed"] = serde_json::json!(speed);
+        }
+        "schedule-message" => {
+            // { message, at?: unixMs, delayMs?: number, expiresAfterMs?: number, id?: string }
+            // `message` is a trigger-message payload.
+            let Some(p) = &payload.payload else {
+                return command_error("invalid-payload", "Missing payload");
+            };
+            let Some(message) = p.get("message").cloned() else {
+                return command_error("invalid-payload", "Missing message");
+            };
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            let at = match (p.get("at").and_then(|v| v.as_u64()), p.get("delayMs").and_then(|v| v.as_u64())) {
+                (Some(at), _) => at,
+                (None, Some(delay)) => now + delay,
+                (None, None) => return command_error("invalid-payload", "Missing at or delayMs"),
+            };
+            let id = state.app_state_sync.schedule_message(ScheduledMessage {
+                id: p.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                at,
+                payload: message,
+                expires_after_ms: p.get("expiresAfterMs").and_then(|v| v.as_u64()),
+            });
+            println!("[schedule] Scheduled {} at {}", id, at);
+            response["id"] = serde_json::json!(id);
+        }
+        "cancel-scheduled" => {
+            let Some(id) = payload.payload.as_ref().and_then(|p| p.get("id")).and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing id");
+            };
+            if !state.app_state_sync.cancel_scheduled(id) {
+                return command_error("unknown-schedule", format!("No scheduled message: {}", id));
+            }
+        }
+        "schedule-recurring" => {
+            // { messageId, intervalMs, until?: unixMs, id?: string } - first firing is one interval from now
+            let Some(p) = &payload.payload else {
+                return command_error("invalid-payload", "Missing payload");
+            };
+            let Some(message_id) = p.get("messageId").and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing messageId");
+            };
+            let Some(interval_ms) = p.get("intervalMs").and_then(|v| v.as_u64()) else {
+                return command_error("invalid-payload", "Missing intervalMs");
+            };
+            if interval_ms < MIN_RECURRING_INTERVAL_MS {
+                return command_error(
+                    "invalid-payload",
+                    format!("intervalMs must be at least {}", MIN_RECURRING_INTERVAL_MS),
+                );
+            }
+            let known = state.app_state_sync.messages.lock()
+                .map(|m| m.iter().any(|m| m.id == message_id))
+                .unwrap_or(false);
+            if !known {
+                return command_error("unknown-message", format!("No message with id: {}", message_id));
+            }
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            let id = state.app_state_sync.schedule_recurring(RecurringSchedule {
+                id: p.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                message_id: message_id.to_string(),
+                interval_ms,
+                until: p.get("until").and_then(|v| v.as_u64()),
+                next_at: now + interval_ms,
+            });
+            println!("[schedule] Recurring {} every {}ms", id, interval_ms);
+            response["id"] = serde_json::json!(id);
+        }
+        "cancel-recurring" => {
+            let Some(id) = payload.payload.as_ref().and_then(|p| p.get("id")).and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing id");
+            };
+            if !state.app_state_sync.cancel_recurring(id) {
+                return command_error("unknown-schedule", format!("No recurring schedule: {}", id));
+            }
+        }
+        "set-countdown" => {
+            // { targetEpochMs, label? }
+            let p = payload.payload.as_ref();
+            let Some(target) = p.and_then(|p| p.get("targetEpochMs")).and_then(|v| v.as_u64()) else {
+                return command_error("invalid-payload", "set-countdown requires { targetEpochMs }");
+            };
+            let label = p.and_then(|p| p.get("label"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+            if let Ok(mut c) = state.app_state_sync.countdown.lock() {
+                *c = Some(Countdown { target, label });
+            }
+        }
+        "clear-countdown" => {
+            if let Ok(mut c) = state.app_state_sync.countdown.lock() {
+                *c = None;
+            }
+        }
+        "toggle-fullscreen" => {
+            // { fullscreen?: boolean } - toggles when omitted
+            let fullscreen = payload.payload.as_ref()
+                .and_then(|p| p.get("fullscreen"))
+                .and_then(|v| v.as_bool());
+            match set_viz_fullscreen(&state.app_handle, &state.app_state_sync, fullscreen) {
+                Ok(now_fullscreen) => response["fullscreen"] = serde_json::json!(now_fullscreen),
+                Err(e) => return command_error("window-error", e),
+            }
+        }
+        "extract-palette" => {
+            // { path, count?: number } - dominant colors of an image, most common first
+            let Some(path) = payload.payload.as_ref().and_then(|p| p.get("path")).and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing path");
+            };
+            let count = payload.payload.as_ref()
+                .and_then(|p| p.get("count"))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(5)
+                .clamp(1, 16) as usize;
+            let resolved = match resolve_media_path(&state, path) {
+                Ok(p) => p,
+                Err(e) => return command_error("invalid-path", e),
+            };
+            let resolved = match allowed_media_file(&state, &resolved) {
+                Ok(p) => p.to_string_lossy().to_string(),
+                Err(status) => return media_path_error(status, &resolved),
+            };
+            let mtime = match std::fs::metadata(&resolved).and_then(|m| m.modified()) {
+                Ok(t) => t,
+                Err(e) => return command_error("not-found", format!("{}: {}", resolved, e)),
+            };
+            if let Some(e) = media_probe::heif_unsupported(Path::new(&resolved)) {
+                return command_error("unsupported-format", e);
+            }
+
+            let cache_key = format!("{}#{}", resolved, count);
+            let cached = state.palette_cache.lock()
+                .ok()
+                .and_then(|c| c.get(&cache_key).filter(|(t, _)| *t == mtime).map(|(_, p)| p.clone()));
+            let palette = match cached {
+                Some(palette) => palette,
+                None => {
+                    let image_path = resolved.clone();
+                    let result = tokio::task::spawn_blocking(move || palette::extract_palette(&image_path, count))
+                        .await
+                        .map_err(|e| e.to_string())
+                        .and_then(|r| r);
+                    match result {
+                        Ok(palette) => {
+                            if let Ok(mut c) = state.palette_cache.lock() {
+                                c.insert(cache_key, (mtime, palette.clone()));
                             }
+                            palette
                         }
+                        Err(e) => return command_error("image-error", e),
                     }
                 }
+            };
+            response["palette"] = serde_json::json!(palette);
+        }
+        "probe-media" => {
+            // { path } -> { kind, width?, height?, durationMs? }
+            let Some(path) = payload.payload.as_ref().and_then(|p| p.get("path")).and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing path");
+            };
+            let resolved = match resolve_media_path(&state, path) {
+                Ok(p) => p,
+                Err(e) => return command_error("invalid-path", e),
+            };
+            let resolved = match allowed_media_file(&state, &resolved) {
+                Ok(p) if p.is_file() => p.to_string_lossy().to_string(),
+                Ok(_) => return media_path_error(StatusCode::NOT_FOUND, &resolved),
+                Err(status) => return media_path_error(status, &resolved),
+            };
+            if let Some(e) = media_probe::heif_unsupported(Path::new(&resolved)) {
+                return command_error("unsupported-format", e);
+            }
+            let result = tokio::task::spawn_blocking(move || media_probe::probe_media(&resolved))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|r| r);
+            match result {
+                Ok(info) => response["media"] = serde_json::json!(info),
+                Err(e) => return command_error("media-error", e),
+            }
+        }
+        "pregenerate-thumbnails" => {
+            // { folder, size? } -> { total }; generation continues in the background
+            let p = payload.payload.as_ref();
+            let Some(folder) = p.and_then(|p| p.get("folder")).and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "pregenerate-thumbnails requires { folder }");
+            };
+            let size = thumbnails::clamp_size(
+                p.and_then(|p| p.get("size")).and_then(|v| v.as_u64()).map(|s| s.min(u32::MAX as u64) as u32),
+            );
+            let resolved = match resolve_media_path(&state, folder) {
+                Ok(p) => p,
+                Err(e) => return command_error("invalid-path", e),
+            };
+            let dir = match allowed_media_file(&state, &resolved) {
+                Ok(dir) if dir.is_dir() => dir,
+                Ok(_) => return media_path_error(StatusCode::NOT_FOUND, &resolved),
+                Err(status) => return media_path_error(status, &resolved),
+            };
+            let cache_dir = match thumbnail_cache_dir(&state) {
+                Ok(dir) => dir,
+                Err(e) => return command_error("image-error", e),
+            };
+            // Only formats the decoder handles; videos and HEIC get no thumbnail. Files that
+            // symlink out of the folder are skipped.
+            let roots = [dir.clone()];
+            let mut files: Vec<_> = media_files_in(&dir)
+                .into_iter()
+                .filter(|f| image::ImageFormat::from_path(f).is_ok_and(|fmt| fmt.reading_enabled()))
+                .filter(|f| check_media_path(f, &roots).is_ok())
+                .collect();
+            files.sort();
+            response["total"] = serde_json::json!(files.len());
+            tokio::spawn(pregenerate_thumbnails(state.clone(), folder.to_string(), files, cache_dir, size));
+        }
+        "stop-preview" => {
+            let was_preview = state.app_state_sync.folder_playback_queue.lock()
+                .map(|mut queue| {
+                    let is_preview = queue.as_ref().map(|q| q.preview).unwrap_or(false);
+                    if is_preview {
+                        *queue = None;
+                    }
+                    is_preview
+                })
+                .unwrap_or(false);
+            if was_preview {
+                println!("[stop-preview] Stopping folder preview");
+                let clear_cmd = serde_json::json!({
+                    "command": "clear-message",
+                    "payload": null
+                });
+                let _ = state.app_handle.emit("remote-command", clear_cmd);
+            }
+        }
+        "pause-folder-playback" => {
+            let paused = state.app_state_sync.folder_playback_queue.lock()
+                .map(|mut queue| queue.as_mut().map(|q| q.paused = true).is_some())
+                .unwrap_or(false);
+            if !paused {
+                return command_error("no-queue", "No folder queue is playing");
+            }
+            println!("[pause-folder-playback] Queue paused");
+        }
+        "resume-folder-playback" => {
+            // { retrigger?: bool } - replay the current message instead of continuing after it
+            let retrigger = payload.payload.as_ref()
+                .and_then(|p| p.get("retrigger"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let current = state.app_state_sync.folder_playback_queue.lock()
+                .ok()
+                .and_then(|mut queue| {
+                    let q = queue.as_mut()?;
+                    q.paused = false;
+                    let id = q.message_ids.get(q.current_index)?.clone();
+                    let mut msg = state.app_state_sync.messages.lock().ok()?
+                        .iter()
+                        .find(|m| m.id == id)
+                        .cloned();
+                    if let Some(msg) = msg.as_mut() {
+                        q.apply_speed(msg);
+                    }
+                    Some((id, msg))
+                });
+            let Some((current_id, current_msg)) = current else {
+                return command_error("no-queue", "No folder queue is playing");
+            };
+            println!("[resume-folder-playback] Queue resumed at {}", current_id);
+
+            let still_playing = state.app_state_sync.active_message_id.lock()
+                .map(|a| a.as_deref() == Some(current_id.as_str()))
+                .unwrap_or(false);
+            if retrigger {
+                if let Some(mut msg) = current_msg {
+                    refresh_text_file(&state, &mut msg).await;
+                    state.app_state_sync.set_active_message(Some(&msg.id));
+                    apply_visualization_override(&state, Some(&msg));
+                    triggered_message = Some(msg.clone());
+                    let trigger_cmd = serde_json::json!({
+                        "command": "trigger-message",
+                        "payload": msg
+                    });
+                    let _ = state.app_handle.emit("remote-command", trigger_cmd);
+                }
+            } else if !still_playing {
+                // The current message finished while paused: do the advance that was held back
+                let complete = RemoteCommand {
+                    command: "message-complete".to_string(),
+                    payload: Some(serde_json::json!({ "messageId": current_id })),
+                };
+                return replay_command(state.clone(), complete).await;
             }
         }
         "cancel-folder-playback" => {
@@ -769,6 +2191,7 @@ async fn handle_command(
             if let Ok(mut queue) = state.app_state_sync.folder_playback_queue.lock() {
                 *queue = None;
             }
+            state.app_state_sync.set_active_message(None);
             
             // Emit clear-message to Tauri windows to stop visualizer
             let clear_cmd = serde_json::json!({
@@ -777,9 +2200,364 @@ async fn handle_command(
             });
             let _ = state.app_handle.emit("remote-command", clear_cmd);
         }
+        "stop-all" => {
+            // Panic button: stop the active message, any queue playback, queued triggers and
+            // schedules. The triggered message is cleared by the broadcast below
+            // (triggered_message stays None).
+            if let Some(r) = require_confirmation(&state, &payload) {
+                return r;
+            }
+            println!("[stop-all] Stopping all playback");
+
+            if let Ok(mut queue) = state.app_state_sync.folder_playback_queue.lock() {
+                *queue = None;
+            }
+            if let Ok(mut queue) = state.app_state_sync.message_queue.lock() {
+                queue.clear();
+            }
+            state.app_state_sync.clear_schedules();
+            state.app_state_sync.set_active_message(None);
+            apply_visualization_override(&state, None);
+
+            // Optionally blank the display by dimming it fully
+            let blank = payload.payload.as_ref()
+                .and_then(|p| p.get("blank"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if blank {
+                state.app_state_sync.blank_display();
+                emit_common_settings(&state);
+            }
+
+            let clear_cmd = serde_json::json!({
+                "command": "clear-message",
+                "payload": null
+            });
+            let _ = state.app_handle.emit("remote-command", clear_cmd);
+        }
+        "bulk-set-text-style-preset" => {
+            // { messageIds: string[], presetId: string }
+            let Some(p) = &payload.payload else {
+                return command_error("invalid-payload", "Missing payload");
+            };
+            let Some(preset_id) = p.get("presetId").and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing presetId");
+            };
+            let message_ids: Vec<String> = p.get("messageIds")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default();
+
+            let text_style_id = state.app_state_sync.text_style_presets.lock()
+                .ok()
+                .and_then(|presets| presets.iter().find(|p| p.id == preset_id).map(|p| p.text_style_id.clone()));
+            let Some(text_style_id) = text_style_id else {
+                return command_error("unknown-preset", format!("Text style preset not found: {}", preset_id));
+            };
+
+            let mut updated: Vec<String> = Vec::new();
+            if let Ok(mut messages) = state.app_state_sync.messages.lock() {
+                for msg in messages.iter_mut().filter(|m| message_ids.contains(&m.id)) {
+                    msg.text_style_preset = Some(preset_id.to_string());
+                    msg.text_style = text_style_id.clone();
+                    updated.push(msg.id.clone());
+                }
+            }
+            if let Ok(mut tree) = state.app_state_sync.message_tree.lock() {
+                for id in &message_ids {
+                    let found = update_tree_message(&mut tree, id, &mut |msg| {
+                        msg.insert("textStylePreset".to_string(), serde_json::json!(preset_id));
+                        msg.insert("textStyle".to_string(), serde_json::json!(text_style_id));
+                    });
+                    if found && !updated.contains(id) {
+                        updated.push(id.clone());
+                    }
+                }
+            }
+
+            let missing: Vec<&String> = message_ids.iter().filter(|id| !updated.contains(id)).collect();
+            response["updated"] = serde_json::json!(updated);
+            response["missing"] = serde_json::json!(missing);
+        }
+        "set-event-name" => {
+            // { name: string | null } or a bare string; empty clears it
+            let name = payload.payload.as_ref()
+                .and_then(|p| p.as_str().or_else(|| p.get("name").and_then(|v| v.as_str())))
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+            println!("[event] Event name: {:?}", name);
+            if let Ok(mut n) = state.app_state_sync.event_name.lock() {
+                *n = name;
+            }
+        }
+        "patch-message-style" => {
+            // { messageId: string, overrides: object } - null values remove keys
+            let Some(p) = &payload.payload else {
+                return command_error("invalid-payload", "Missing payload");
+            };
+            let Some(message_id) = p.get("messageId").and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing messageId");
+            };
+            let Some(overrides) = p.get("overrides").filter(|v| v.is_object()) else {
+                return command_error("invalid-payload", "overrides must be an object");
+            };
+
+            let mut merged: Option<serde_json::Value> = None;
+            if let Ok(mut messages) = state.app_state_sync.messages.lock() {
+                if let Some(msg) = messages.iter_mut().find(|m| m.id == message_id) {
+                    let mut current = msg.style_overrides.take().unwrap_or(serde_json::Value::Null);
+                    merge_json_patch(&mut current, overrides);
+                    msg.style_overrides = Some(current.clone());
+                    merged = Some(current);
+                }
+            }
+            if let Ok(mut tree) = state.app_state_sync.message_tree.lock() {
+                update_tree_message(&mut tree, message_id, &mut |msg| {
+                    let current = msg.entry("styleOverrides".to_string()).or_insert(serde_json::Value::Null);
+                    merge_json_patch(current, overrides);
+                    if merged.is_none() {
+                        merged = Some(current.clone());
+                    }
+                });
+            }
+            let Some(merged) = merged else {
+                return command_error("unknown-message", format!("Message not found: {}", message_id));
+            };
+            response["styleOverrides"] = merged;
+        }
+        "set-auto-dim" => {
+            // { afterMs: number | null, floor?: number }
+            if let Some(p) = &payload.payload {
+                if let Ok(mut m) = state.app_state_sync.auto_dim.lock() {
+                    m.after_ms = p.get("afterMs").and_then(|v| v.as_u64());
+                    if let Some(floor) = p.get("floor").and_then(|v| v.as_f64()) {
+                        m.floor = floor.clamp(0.0, 1.0);
+                    }
+                }
+            }
+        }
+        "shutdown" => {
+            // Destructive, so opt-in
+            if !remote_shutdown_allowed() {
+                return command_error(
+                    "disabled",
+                    "Remote shutdown is disabled; set VIBECAST_ALLOW_REMOTE_SHUTDOWN=1 to enable it",
+                );
+            }
+            println!("[shutdown] Remote shutdown requested");
+            state.app_state_sync.log_event("shutdown", None);
+            // Ends SSE streams and stops the server accepting connections
+            state.shutdown_tx.send_replace(true);
+            let app_handle = state.app_handle.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(SHUTDOWN_GRACE).await;
+                let audio_handle = app_handle.clone();
+                let _ = tokio::task::spawn_blocking(move || {
+                    if let Some(audio) = audio_handle.try_state::<AudioState>() {
+                        audio.shutdown();
+                    }
+                }).await;
+                app_handle.exit(0);
+            });
+            response["shuttingDown"] = serde_json::json!(true);
+        }
+        "apply-theme" => {
+            // { accent?, background?, text? } hex colors
+            let Some(theme) = payload.payload.as_ref()
+                .and_then(|p| serde_json::from_value::<Theme>(p.clone()).ok())
+            else {
+                return command_error("invalid-payload", "Expected { accent?, background?, text? }");
+            };
+            if let Err(e) = theme.validate() {
+                return command_error("invalid-payload", e);
+            }
+            let changed = state.app_state_sync.apply_theme(&theme);
+            response["changed"] = serde_json::json!(changed);
+            emit_visualization_presets(&state);
+        }
+        "reset-theme" => {
+            if !state.app_state_sync.reset_theme() {
+                return command_error("no-theme", "No theme is applied");
+            }
+            emit_visualization_presets(&state);
+        }
+        "set-audio-reactive" => {
+            // `true`/`false` or { enabled }
+            let enabled = payload.payload.as_ref()
+                .and_then(|p| p.as_bool().or_else(|| p.get("enabled").and_then(|v| v.as_bool())));
+            let Some(enabled) = enabled else {
+                return command_error("invalid-payload", "Expected a boolean or { enabled }");
+            };
+            if let Ok(mut m) = state.app_state_sync.common_settings.lock() {
+                m.audio_reactive = enabled;
+            }
+            emit_common_settings(&state);
+        }
+        "reload-config" => {
+            // Re-read the launch config file -> { path }
+            let known = state.app_state_sync.launch_config.lock().map(|c| c.is_some()).unwrap_or(false);
+            if !known {
+                return command_error("no-config-path", "No config path is known (the app was started without a config file)");
+            }
+            match state.app_state_sync.reload_config() {
+                Ok(path) => {
+                    println!("[reload-config] Reloaded {}", path);
+                    response["path"] = serde_json::json!(path);
+                }
+                Err(e) => return command_error("reload-failed", e),
+            }
+            emit_visualization_presets(&state);
+            emit_common_settings(&state);
+        }
+        "set-idle-speed" => {
+            // number, or { speed }; clamped to 0..5
+            let speed = payload.payload.as_ref()
+                .and_then(|p| p.as_f64().or_else(|| p.get("speed").and_then(|v| v.as_f64())));
+            let Some(speed) = speed.filter(|s| s.is_finite()) else {
+                return command_error("invalid-payload", "Expected a number or { speed }");
+            };
+            let speed = speed.clamp(IDLE_SPEED_MIN, IDLE_SPEED_MAX);
+            if let Ok(mut m) = state.app_state_sync.common_settings.lock() {
+                m.idle_speed = speed;
+            }
+            response["speed"] = serde_json::json!(speed);
+            emit_common_settings(&state);
+        }
+        "set-youtube-volume" => {
+            // 0..100, or { volume }
+            let volume = payload.payload.as_ref()
+                .and_then(|p| p.as_f64().or_else(|| p.get("volume").and_then(|v| v.as_f64())));
+            let Some(volume) = volume.filter(|v| v.is_finite()) else {
+                return command_error("invalid-payload", "Expected a number or { volume }");
+            };
+            let volume = volume.clamp(0.0, 100.0);
+            set_youtube_setting(&state, "volume", serde_json::json!(volume));
+            response["volume"] = serde_json::json!(volume);
+        }
+        "set-youtube-muted" => {
+            // `true`/`false` or { muted }
+            let muted = payload.payload.as_ref()
+                .and_then(|p| p.as_bool().or_else(|| p.get("muted").and_then(|v| v.as_bool())));
+            let Some(muted) = muted else {
+                return command_error("invalid-payload", "Expected a boolean or { muted }");
+            };
+            set_youtube_setting(&state, "muted", serde_json::json!(muted));
+        }
+        "set-output-aspect" => {
+            // "16:9", { aspect: "16:9" }, or null to clear
+            let aspect = match payload.payload.as_ref() {
+                None | Some(serde_json::Value::Null) => None,
+                Some(p) => match p.as_str().or_else(|| p.get("aspect").and_then(|v| v.as_str())) {
+                    Some(a) if is_valid_aspect(a) => Some(a.trim().to_string()),
+                    Some(a) => return command_error("invalid-payload", format!("Invalid aspect ratio '{}' (expected W:H, e.g. 16:9)", a)),
+                    None if p.get("aspect").is_some_and(|v| v.is_null()) => None,
+                    None => return command_error("invalid-payload", "Expected an aspect string, { aspect }, or null"),
+                },
+            };
+            if let Ok(mut m) = state.app_state_sync.common_settings.lock() {
+                m.output_aspect = aspect;
+            }
+            emit_common_settings(&state);
+        }
+        "freeze-audio" | "unfreeze-audio" => {
+            // Audio state is managed by the Tauri app; absent when audio capture isn't running
+            if let Some(audio) = state.app_handle.try_state::<AudioState>() {
+                if payload.command == "freeze-audio" {
+                    audio.freeze();
+                } else {
+                    audio.unfreeze();
+                }
+            }
+        }
+        "resolve-path" => {
+            // Accepts either "path" or { path: "..." }
+            let input = payload.payload.as_ref()
+                .and_then(|p| p.as_str().or_else(|| p.get("path").and_then(|v| v.as_str())));
+            let Some(input) = input else {
+                return command_error("invalid-payload", "Missing path");
+            };
+            response["result"] = describe_resolved_path(&state, input);
+        }
+        "start-recording" => {
+            let Some(name) = payload.payload.as_ref().and_then(|p| p.get("name")).and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing macro name");
+            };
+            println!("[macro] Recording '{}'", name);
+            state.app_state_sync.start_recording(name);
+        }
+        "stop-recording" => {
+            let Some((name, count)) = state.app_state_sync.stop_recording() else {
+                return command_error("not-recording", "No macro is being recorded");
+            };
+            println!("[macro] Saved '{}' with {} commands", name, count);
+            response["name"] = serde_json::json!(name);
+            response["commandCount"] = serde_json::json!(count);
+        }
+        "list-macros" => {
+            let macros = state.app_state_sync.macros.lock()
+                .map(|m| {
+                    let mut list: Vec<serde_json::Value> = m.iter()
+                        .map(|(name, commands)| serde_json::json!({
+                            "name": name,
+                            "commandCount": commands.len(),
+                            "durationMs": commands.last().map(|c| c.offset_ms).unwrap_or(0)
+                        }))
+                        .collect();
+                    list.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+                    list
+                })
+                .unwrap_or_default();
+            response["macros"] = serde_json::json!(macros);
+        }
+        "play-macro" => {
+            let Some(name) = payload.payload.as_ref().and_then(|p| p.get("name")).and_then(|v| v.as_str()) else {
+                return command_error("invalid-payload", "Missing macro name");
+            };
+            let commands = state.app_state_sync.macros.lock()
+                .ok()
+                .and_then(|m| m.get(name).cloned());
+            let Some(commands) = commands else {
+                return command_error("unknown-macro", format!("Macro not found: {}", name));
+            };
+            println!("[macro] Playing '{}' ({} commands)", name, commands.len());
+            // Replay with the original relative timing
+            let replay_state = state.clone();
+            tokio::spawn(async move {
+                let mut elapsed_ms = 0;
+                for recorded in commands {
+                    if recorded.offset_ms > elapsed_ms {
+                        tokio::time::sleep(Duration::from_millis(recorded.offset_ms - elapsed_ms)).await;
+                        elapsed_ms = recorded.offset_ms;
+                    }
+                    replay_command(replay_state.clone(), recorded.command).await;
+                }
+            });
+        }
+        "validate-media" => {
+            let results = validate_media_references(&state);
+            let broken = results.iter()
+                .filter(|r| !r.get("exists").and_then(|v| v.as_bool()).unwrap_or(false))
+                .count();
+            println!("[validate-media] {} references checked, {} broken", results.len(), broken);
+            response["results"] = serde_json::json!(results);
+        }
         "reset-message-stats" => {
+            // { messageId } resets a single message; no payload clears everything
+            if let Some(r) = require_confirmation(&state, &payload) {
+                return r;
+            }
+            let message_id = payload.payload.as_ref()
+                .and_then(|p| p.get("messageId"))
+                .and_then(|v| v.as_str());
             if let Ok(mut m) = state.app_state_sync.message_stats.lock() {
-                *m = serde_json::json!({});
+                match message_id {
+                    Some(id) => {
+                        if let Some(obj) = m.as_object_mut() {
+                            obj.remove(id);
+                        }
+                    }
+                    None => *m = serde_json::json!({}),
+                }
             }
         }
         "load-configuration" => {
@@ -822,7 +2600,7 @@ async fn handle_command(
                         *t = tree.clone();
                     }
                     // Ensure flattened messages match tree
-                    let flat = flatten_message_tree(tree);
+                    let flat = flatten_message_tree_value(tree);
                     if let Ok(mut m) = state.app_state_sync.messages.lock() {
                         *m = flat;
                     }
@@ -882,7 +2660,117 @@ async fn handle_command(
     // Also emit to Tauri windows (for VibeCast which uses Tauri events for audio sync)
     let _ = state.app_handle.emit("remote-command", &payload);
 
-    Json(serde_json::json!({ "status": "ok" }))
+    Json(response).into_response()
+}
+
+/// Record a trigger of `message_id` in the message stats
+fn record_trigger_stats(state: &AppState, message_id: &str) {
+    if let Ok(mut stats) = state.app_state_sync.message_stats.lock() {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        
+        let current_stats: serde_json::Value = stats.get(message_id)
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({
+                "messageId": message_id,
+                "triggerCount": 0,
+                "lastTriggered": 0,
+                "history": []
+            }));
+        
+        let trigger_count = current_stats.get("triggerCount")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) + 1;
+        
+        let mut history = current_stats.get("history")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        
+        history.push(serde_json::json!({ "timestamp": timestamp }));
+        // Keep last 50 entries
+        if history.len() > 50 {
+            history = history.into_iter().rev().take(50).rev().collect();
+        }
+        
+        let new_stats = serde_json::json!({
+            "messageId": message_id,
+            "triggerCount": trigger_count,
+            "lastTriggered": timestamp,
+            "history": history
+        });
+        
+        if let Some(obj) = stats.as_object_mut() {
+            obj.insert(message_id.to_string(), new_stats);
+        } else {
+            *stats = serde_json::json!({ message_id: new_stats });
+        }
+    }
+}
+
+/// Next message from the `queue: true` trigger queue after `completed_id` finishes, if any
+fn next_queued_message(state: &AppState, completed_id: &str) -> Option<MessageConfig> {
+    let msg = state.app_state_sync.advance_message_queue(completed_id)?;
+    println!("[queue] Playing queued message {}", msg.id);
+    record_trigger_stats(state, &msg.id);
+    state.app_state_sync.set_triggered_target(TRIGGER_TARGET_ALL);
+    Some(msg)
+}
+
+/// Set up a folder playback queue and emit its first message to the Tauri windows.
+/// Returns the first message, or `None` if the folder has no messages.
+fn start_folder_queue(state: &AppState, folder_id: &str, preview: bool, shuffle: bool, loop_playback: bool) -> Option<MessageConfig> {
+    // Get message tree and collect message IDs from the folder
+    let message_ids = if let Ok(tree) = state.app_state_sync.message_tree.lock() {
+        collect_messages_from_folder(folder_id, &tree)
+    } else {
+        vec![]
+    };
+    if message_ids.is_empty() {
+        return None;
+    }
+    let mut new_queue = FolderPlaybackQueue {
+        folder_id: folder_id.to_string(),
+        message_ids,
+        current_index: 0,
+        preview,
+        speed_multiplier: None,
+        paused: false,
+        shuffle,
+        loop_playback,
+    };
+    if shuffle {
+        new_queue.shuffle_order(None);
+    }
+    let first_id = new_queue.message_ids[0].clone();
+
+    // Set up the queue
+    if let Ok(mut queue) = state.app_state_sync.folder_playback_queue.lock() {
+        *queue = Some(new_queue);
+    }
+
+    // Trigger the first message
+    let msg = state.app_state_sync.messages.lock()
+        .ok()
+        .and_then(|messages| messages.iter().find(|m| m.id == first_id).cloned())?;
+    state.app_state_sync.set_active_message(Some(&msg.id));
+    apply_visualization_override(state, Some(&msg));
+
+    // Emit trigger-message remote command to Tauri windows
+    // This ensures VisualizerWindow receives the command and actually plays the message
+    let trigger_cmd = serde_json::json!({
+        "command": "trigger-message",
+        "payload": msg
+    });
+    let _ = state.app_handle.emit("remote-command", trigger_cmd);
+    Some(msg)
+}
+
+/// Re-dispatch a command through `handle_command` (boxed, since it is called from within it)
+fn replay_command(state: AppState, command: RemoteCommand) -> futures::future::BoxFuture<'static, Response> {
+    Box::pin(handle_command(State(state), ApiJson(command)))
 }
 
 async fn get_state(State(state): State<AppState>) -> Json<serde_json::Value> {
@@ -891,13 +2779,27 @@ async fn get_state(State(state): State<AppState>) -> Json<serde_json::Value> {
     Json(serde_json::to_value(&current).unwrap_or(serde_json::json!({})))
 }
 
+/// Build info of the running app, so deployments on several machines can be compared
+async fn get_version(State(state): State<AppState>) -> Json<VersionInfo> {
+    let info = state.app_state_sync.version_info.lock()
+        .ok()
+        .and_then(|v| v.clone())
+        .unwrap_or_else(|| VersionInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: None,
+            build_time: None,
+            tauri_version: tauri::VERSION.to_string(),
+        });
+    Json(info)
+}
+
 async fn get_status() -> Json<serde_json::Value> {
     Json(serde_json::json!({ "status": "online" }))
 }
 
 async fn handle_e2e_report(
     State(state): State<AppState>,
-    Json(report): Json<E2EReport>,
+    ApiJson(report): ApiJson<E2EReport>,
 ) -> Json<serde_json::Value> {
     println!("[E2E] Received report: {:?}", report);
     if let Ok(mut m) = state.app_state_sync.last_e2e_report.lock() {
@@ -913,31 +2815,438 @@ async fn get_last_e2e_report(State(state): State<AppState>) -> Json<Option<E2ERe
     Json(report)
 }
 
+async fn resolve_path_info(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    match params.get("path") {
+        Some(path) => Json(describe_resolved_path(&state, path)).into_response(),
+        None => (StatusCode::BAD_REQUEST, "Missing path parameter").into_response(),
+    }
+}
+
+/// Peek at the folder queue: `{ nextMessage }`, null when no queue is running or it is on
+/// its last message
+async fn get_queue_next(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "nextMessage": state.app_state_sync.peek_next_queue_message()
+    }))
+}
+
+/// The message tree as a Markdown outline, titled with the event name if set
+async fn export_tree_markdown(State(state): State<AppState>) -> Response {
+    let outline = state.app_state_sync.message_tree.lock()
+        .map(|t| MessageTree::from_value(&t).to_markdown())
+        .unwrap_or_default();
+    let title = state.app_state_sync.event_name.lock()
+        .ok()
+        .and_then(|n| n.clone())
+        .unwrap_or_else(|| "Messages".to_string());
+    (
+        [
+            (header::CONTENT_TYPE, "text/markdown; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "inline; filename=\"tree.md\""),
+        ],
+        format!("# {}\n\n{}", title, outline),
+    ).into_response()
+}
+
+/// Known text styles with their metadata, each with the current presets for that style
+async fn get_text_styles(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let presets = state.app_state_sync.text_style_presets.lock()
+        .map(|p| p.clone())
+        .unwrap_or_default();
+    let styles: Vec<_> = KNOWN_TEXT_STYLES.iter()
+        .map(|style| {
+            let mut entry = serde_json::json!(style);
+            entry["presets"] = serde_json::json!(presets.iter()
+                .filter(|p| p.text_style_id == style.id)
+                .collect::<Vec<_>>());
+            entry
+        })
+        .collect();
+    Json(serde_json::json!(styles))
+}
+
+/// Known visualizations (id and name), flagged with whether each is enabled
+async fn get_visualizations(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let enabled = state.app_state_sync.enabled_visualizations.lock()
+        .map(|e| e.clone())
+        .unwrap_or_default();
+    let visualizations: Vec<_> = KNOWN_VISUALIZATIONS.iter()
+        .map(|viz| {
+            let mut entry = serde_json::json!(viz);
+            entry["enabled"] = serde_json::json!(enabled.iter().any(|id| id == viz.id));
+            entry
+        })
+        .collect();
+    Json(serde_json::json!(visualizations))
+}
+
+/// All folders in the message tree with their paths and message counts
+async fn get_folders(State(state): State<AppState>) -> Json<Vec<FolderSummary>> {
+    let folders = state.app_state_sync.message_tree.lock()
+        .map(|t| MessageTree::from_value(&t).folders())
+        .unwrap_or_default();
+    Json(folders)
+}
+
+/// Messages in a folder (including nested folders), in tree order
+async fn get_folder_messages(
+    State(state): State<AppState>,
+    AxumPath(folder_id): AxumPath<String>,
+) -> Response {
+    let tree = state.app_state_sync.message_tree.lock()
+        .map(|t| MessageTree::from_value(&t))
+        .unwrap_or_default();
+    match tree.collect_folder(&folder_id) {
+        Some(messages) => Json(messages).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("Folder not found: {}", folder_id)).into_response(),
+    }
+}
+
+/// Recently triggered messages, most recent first: `?limit=N` (default 10)
+async fn get_recent_messages(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<Vec<MessageConfig>> {
+    let limit = params.get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(10)
+        .min(RECENT_MESSAGES_CAPACITY);
+    Json(state.app_state_sync.recent_messages(limit))
+}
+
+async fn get_message_count(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let count = state.app_state_sync.messages.lock().map(|m| m.len()).unwrap_or(0);
+    Json(serde_json::json!({ "count": count }))
+}
+
+/// Cheap aggregate counts for dashboard tiles
+async fn get_summary(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let sync = &state.app_state_sync;
+    let message_count = sync.messages.lock().map(|m| m.len()).unwrap_or(0);
+    let folder_count = sync.message_tree.lock().map(|t| MessageTree::from_value(&t).folder_count()).unwrap_or(0);
+    let preset_count = sync.visualization_presets.lock().map(|p| p.len()).unwrap_or(0);
+    let enabled_visualizations = sync.enabled_visualizations.lock()
+        .map(|v| v.clone())
+        .unwrap_or_default();
+    let active_visualization = sync.active_visualization.lock()
+        .map(|v| v.clone())
+        .unwrap_or_default();
+    let total_triggers: u64 = sync.message_stats.lock()
+        .map(|stats| {
+            stats.as_object()
+                .map(|obj| obj.values().filter_map(|s| s.get("triggerCount").and_then(|v| v.as_u64())).sum())
+                .unwrap_or(0)
+        })
+        .unwrap_or(0);
+
+    Json(serde_json::json!({
+        "messageCount": message_count,
+        "folderCount": folder_count,
+        "presetCount": preset_count,
+        "enabledVisualizations": enabled_visualizations,
+        "activeVisualization": active_visualization,
+        "totalTriggers": total_triggers
+    }))
+}
+
+/// Recent captured stdout/stderr lines, oldest first (`?limit=N`, default 200)
+async fn get_logs(Query(params): Query<HashMap<String, String>>) -> Json<Vec<log_capture::LogLine>> {
+    let limit = params.get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(200)
+        .min(log_capture::LOG_BUFFER_CAPACITY);
+    Json(log_capture::recent_log_lines(limit))
+}
+
+/// Record who sent each `/api/command` and what it was. The body is buffered to read the
+/// command name, then handed on unchanged.
+async fn access_log_middleware(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_COMMAND_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => return (StatusCode::PAYLOAD_TOO_LARGE, e.to_string()).into_response(),
+    };
+    let command = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|v| v.get("command").and_then(|c| c.as_str()).map(|c| c.to_string()));
+
+    let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    state.app_state_sync.record_access(AccessLogEntry {
+        timestamp,
+        // Report IPv4 clients of the dual-stack listener as plain IPv4
+        ip: addr.ip().to_canonical().to_string(),
+        command,
+        status: response.status().as_u16(),
+    });
+    response
+}
+
+/// Command access log, newest last: `?limit=N` returns only the latest N entries
+async fn get_access_log(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<Vec<AccessLogEntry>> {
+    let limit = params.get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(ACCESS_LOG_CAPACITY);
+    let entries = state.app_state_sync.access_log.lock()
+        .map(|log| log.iter().skip(log.len().saturating_sub(limit)).cloned().collect())
+        .unwrap_or_default();
+    Json(entries)
+}
+
+/// Event log tail: `?after=<id>` returns only newer entries, `?limit=N` caps the count
+async fn get_event_log(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<serde_json::Value> {
+    let after = params.get("after").and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+    let limit = params.get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(100)
+        .min(EVENT_LOG_CAPACITY);
+    let entries = state.app_state_sync.event_log_after(after, limit);
+    Json(serde_json::json!({
+        "entries": entries,
+        "lastId": state.app_state_sync.event_log_seq.load(std::sync::atomic::Ordering::Relaxed)
+    }))
+}
+
+async fn validate_media(State(state): State<AppState>) -> Json<Vec<serde_json::Value>> {
+    Json(validate_media_references(&state))
+}
+
+/// Media source of every `photo-slideshow` preset: `{ presetId, folderPath, resolved, exists,
+/// mediaCount }`, to find the preset behind a blank slideshow
+async fn get_slideshow_folders(State(state): State<AppState>) -> Json<Vec<serde_json::Value>> {
+    let presets: Vec<VisualizationPreset> = state.app_state_sync.visualization_presets.lock()
+        .map(|p| p.iter().filter(|p| p.visualization_id == "photo-slideshow").cloned().collect())
+        .unwrap_or_default();
+    let folders = presets.iter()
+        .map(|preset| {
+            let folder_path = preset.settings.get("folderPath").and_then(|v| v.as_str()).unwrap_or("");
+            let resolved = Some(folder_path)
+                .filter(|f| !f.is_empty())
+                .and_then(|f| resolve_media_path(&state, f).ok());
+            let dir = resolved.as_deref().map(Path::new).filter(|p| p.is_dir());
+            serde_json::json!({
+                "presetId": preset.id,
+                "folderPath": folder_path,
+                "resolved": resolved,
+                "exists": dir.is_some(),
+                "mediaCount": dir.map(|d| media_files_in(d).len()).unwrap_or(0),
+            })
+        })
+        .collect();
+    Json(folders)
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Message stats as CSV rows under the header, followed by a zero row for each id in
+/// `untriggered` that has no stats entry
+fn stats_csv(event_name: &str, stats: &serde_json::Value, untriggered: &[String]) -> String {
+    let event_field = csv_field(event_name);
+    let mut csv = String::from("eventName,messageId,triggerCount,lastTriggered\n");
+    let mut seen: Vec<&str> = Vec::new();
+    if let Some(obj) = stats.as_object() {
+        for (message_id, entry) in obj {
+            let trigger_count = entry.get("triggerCount").and_then(|v| v.as_u64()).unwrap_or(0);
+            let last_triggered = entry.get("lastTriggered").and_then(|v| v.as_u64()).unwrap_or(0);
+            csv.push_str(&format!("{},{},{},{}\n", event_field, csv_field(message_id), trigger_count, last_triggered));
+            seen.push(message_id);
+        }
+    }
+    for id in untriggered.iter().filter(|id| !seen.contains(&id.as_str())) {
+        csv.push_str(&format!("{},{},0,0\n", event_field, csv_field(id)));
+    }
+    csv
+}
+
+/// Trigger counts per `bucket` (`minute`, `hour` or `day`, default `hour`), from the
+/// per-message trigger history
+async fn get_stats_histogram(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let bucket = params.get("bucket").map(|b| b.as_str()).unwrap_or("hour");
+    let bucket_ms: u64 = match bucket {
+        "minute" => 60_000,
+        "hour" => 3_600_000,
+        "day" => 86_400_000,
+        other => {
+            return (StatusCode::BAD_REQUEST, format!("Unknown bucket '{}' (expected minute, hour or day)", other))
+                .into_response();
+        }
+    };
+    let histogram = state.app_state_sync.message_stats.lock()
+        .map(|stats| trigger_histogram(&stats, bucket_ms))
+        .unwrap_or_default();
+    let buckets: Vec<_> = histogram.into_iter()
+        .map(|(start, count)| serde_json::json!({ "start": start, "count": count }))
+        .collect();
+    Json(serde_json::json!({
+        "bucket": bucket,
+        "bucketMs": bucket_ms,
+        "buckets": buckets,
+    })).into_response()
+}
+
+/// Export message stats as CSV (`eventName,messageId,triggerCount,lastTriggered`)
+async fn export_stats_csv(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let include_untriggered = params.get("includeUntriggered").map(|v| v == "true").unwrap_or(false);
+
+    let stats = state.app_state_sync.message_stats.lock()
+        .map(|m| m.clone())
+        .unwrap_or_else(|_| serde_json::json!({}));
+
+    // Rows are prefixed with the session's event name so exports from several events can be combined
+    let event_name = state.app_state_sync.event_name.lock()
+        .ok()
+        .and_then(|n| n.clone())
+        .unwrap_or_default();
+    let untriggered: Vec<String> = if include_untriggered {
+        state.app_state_sync.messages.lock()
+            .map(|messages| messages.iter().map(|m| m.id.clone()).collect())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let csv = stats_csv(&event_name, &stats, &untriggered);
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"message-stats.csv\""),
+        ],
+        csv,
+    ).into_response()
+}
+
+/// Full current config (the same document `save_config_to_file` writes) as a download
+async fn export_config(State(state): State<AppState>) -> Response {
+    match serde_json::to_string_pretty(&state.app_state_sync.config_json()) {
+        Ok(json) => (
+            [
+                (header::CONTENT_TYPE, "application/json"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"vibecast-config.json\""),
+            ],
+            json,
+        ).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize config: {}", e)).into_response(),
+    }
+}
+
+/// Apply an uploaded config like `load-configuration` does. A missing or unsupported version
+/// or missing required sections fail with a 400; other sections that don't parse are skipped
+/// as in `load-configuration`, but listed in the response so bad configs can be debugged.
+async fn import_config(State(state): State<AppState>, ApiJson(config): ApiJson<serde_json::Value>) -> Response {
+    if let Err((code, message)) = check_required_config(&config) {
+        return command_error(code, message);
+    }
+
+    let failed_sections = config_section_errors(&config);
+    if !failed_sections.is_empty() {
+        eprintln!("[Server] Importing config with invalid sections: {}", failed_sections.join("; "));
+    }
+    // The command handler applies the config and broadcasts the new state
+    let _ = replay_command(state, RemoteCommand {
+        command: "load-configuration".to_string(),
+        payload: Some(config),
+    }).await;
+
+    Json(serde_json::json!({
+        "status": "ok",
+        "failedSections": failed_sections,
+    })).into_response()
+}
+
+/// SSE keep-alive interval bounds and default, in seconds
+const SSE_KEEPALIVE_DEFAULT_SECS: u64 = 15;
+const SSE_KEEPALIVE_MIN_SECS: u64 = 2;
+const SSE_KEEPALIVE_MAX_SECS: u64 = 120;
+
+/// Serialize a state event, keeping only the `include`d top-level fields if given
+fn state_event_data(broadcast_state: &BroadcastState, include: Option<&HashSet<String>>) -> String {
+    let Some(include) = include else {
+        return serde_json::to_string(broadcast_state).unwrap_or_default();
+    };
+    let mut value = serde_json::to_value(broadcast_state).unwrap_or_default();
+    if let Some(obj) = value.as_object_mut() {
+        obj.retain(|key, _| include.contains(key));
+    }
+    value.to_string()
+}
+
 /// SSE endpoint that streams state updates to clients
 async fn state_events(
     State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    println!("[SSE] Client connected");
+    // Per-connection keep-alive: `?keepalive=<seconds>` (e.g. shorter for flaky phones)
+    let keepalive = params.get("keepalive")
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(|secs| secs.clamp(SSE_KEEPALIVE_MIN_SECS, SSE_KEEPALIVE_MAX_SECS))
+        .unwrap_or(SSE_KEEPALIVE_DEFAULT_SECS);
+    let keepalive = Duration::from_secs(keepalive);
+    // Per-connection projection: `?include=activeVisualization,triggeredMessage` sends only those fields
+    let include: Option<Arc<HashSet<String>>> = params.get("include")
+        .map(|v| v.split(',').map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect())
+        .map(Arc::new);
+    println!("[SSE] Client connected (keep-alive {:?}, include {:?})", keepalive, include);
+    let initial_include = include.clone();
     // Subscribe to the broadcast channels
     let rx_state = state.app_state_sync.state_tx.subscribe();
     let rx_command = state.app_state_sync.command_tx.subscribe();
     
     // Send initial state immediately so clients don't have to wait
     let initial_state = state.app_state_sync.get_state();
+    // Late joiners can catch up on recent transient commands: `?replay=<n>`
+    let replay_count = params.get("replay")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0)
+        .min(COMMAND_REPLAY_CAPACITY);
+    let replayed = state.app_state_sync.recent_commands(replay_count);
     
-    // Convert broadcast receiver to a stream, mapping directly to SSE events
-    // filter_map skips lagged errors (when client is slower than broadcast rate)
+    // Convert broadcast receiver to a stream, mapping directly to SSE events.
+    // A client slower than the broadcast rate lags and misses updates; since every state
+    // event is a full state, sending the current one resynchronizes it.
+    let sync = state.app_state_sync.clone();
     let state_stream = BroadcastStream::new(rx_state)
-        .filter_map(|result| async move { 
-            if result.is_err() {
-                eprintln!("[SSE] State stream lagged");
+        .map(move |result| match result {
+            Ok(broadcast_state) => broadcast_state,
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                eprintln!("[SSE] State stream lagged by {} updates; resending current state", missed);
+                sync.get_state()
             }
-            result.ok() 
         })
-        .map(|broadcast_state: BroadcastState| -> Result<Event, Infallible> {
+        .map(move |broadcast_state: BroadcastState| -> Result<Event, Infallible> {
             Ok(Event::default()
                 .event("state")
-                .data(serde_json::to_string(&broadcast_state).unwrap_or_default()))
+                .data(state_event_data(&broadcast_state, include.as_deref())))
         });
         
     let command_stream = BroadcastStream::new(rx_command)
@@ -958,13 +3267,171 @@ async fn state_events(
         println!("[SSE] Sending initial state");
         Ok(Event::default()
             .event("state")
-            .data(serde_json::to_string(&initial_state).unwrap_or_default()))
+            .data(state_event_data(&initial_state, initial_include.as_deref())))
     });
     
+    // End the stream when the app shuts down so clients see the connection close
+    let mut shutdown_rx = state.shutdown_tx.subscribe();
+    let shutdown = async move {
+        let _ = shutdown_rx.wait_for(|shutting_down| *shutting_down).await;
+    };
+
     // Merge streams
+    let replay_events = futures::stream::iter(replayed).map(|command| -> Result<Event, Infallible> {
+        Ok(Event::default()
+            .event("command")
+            .data(serde_json::to_string(&command).unwrap_or_default()))
+    });
     let combined_stream = initial_event
-        .chain(futures::stream::select(state_stream, command_stream));
+        .chain(replay_events)
+        .chain(futures::stream::select(state_stream, command_stream))
+        .take_until(shutdown);
     
     Sse::new(combined_stream)
-        .keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
-}
\ No newline at end of file
+        .keep_alive(KeepAlive::new().interval(keepalive))
+}
+
+/// One `BroadcastState` JSON document per line, starting with the current state, for
+/// non-browser consumers (`curl -N .../api/stream.ndjson | jq`). The stream ends when the
+/// client disconnects (axum drops it) or the app shuts down.
+async fn state_ndjson(State(state): State<AppState>) -> Response {
+    println!("[NDJSON] Client connected");
+    let rx_state = state.app_state_sync.state_tx.subscribe();
+    let initial_state = state.app_state_sync.get_state();
+
+    let line = |broadcast_state: &BroadcastState| -> Result<String, Infallible> {
+        Ok(format!("{}\n", serde_json::to_string(broadcast_state).unwrap_or_default()))
+    };
+    // As for SSE, a lagging client gets the current state instead of the updates it missed
+    let sync = state.app_state_sync.clone();
+    let updates = BroadcastStream::new(rx_state)
+        .map(move |result| match result {
+            Ok(broadcast_state) => line(&broadcast_state),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                eprintln!("[NDJSON] State stream lagged by {} updates; resending current state", missed);
+                line(&sync.get_state())
+            }
+        });
+
+    let mut shutdown_rx = state.shutdown_tx.subscribe();
+    let shutdown = async move {
+        let _ = shutdown_rx.wait_for(|shutting_down| *shutting_down).await;
+    };
+    let stream = futures::stream::once(async move { line(&initial_state) })
+        .chain(updates)
+        .take_until(shutdown);
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        Body::from_stream(stream),
+    ).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_quotes_delimiters_quotes_and_newlines() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn stats_csv_has_header_and_escaped_rows() {
+        let stats = serde_json::json!({
+            "msg,1": { "triggerCount": 2, "lastTriggered": 1_000 }
+        });
+        let untriggered = ["msg,1".to_string(), "msg-2".to_string()];
+        assert_eq!(
+            stats_csv("Party \"2026\"", &stats, &untriggered),
+            "eventName,messageId,triggerCount,lastTriggered\n\
+             \"Party \"\"2026\"\"\",\"msg,1\",2,1000\n\
+             \"Party \"\"2026\"\"\",msg-2,0,0\n"
+        );
+        assert_eq!(stats_csv("", &serde_json::json!({}), &[]), "eventName,messageId,triggerCount,lastTriggered\n");
+    }
+
+    /// A fresh, empty directory in the system temp dir, unique to this test process
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("vibecast-server-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn media_path_check_allows_files_inside_roots_only() {
+        let dir = temp_dir("media-roots");
+        let root = dir.join("photos");
+        let outside = dir.join("private");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(root.join("a.jpg"), b"jpg").unwrap();
+        std::fs::write(outside.join("secret.txt"), b"secret").unwrap();
+        let roots = [root.clone()];
+
+        let allowed = check_media_path(&root.join("a.jpg"), &roots).unwrap();
+        assert_eq!(allowed, std::fs::canonicalize(root.join("a.jpg")).unwrap());
+        assert_eq!(check_media_path(&root, &roots).unwrap(), std::fs::canonicalize(&root).unwrap());
+        assert_eq!(check_media_path(&root.join("../private/secret.txt"), &roots), Err(StatusCode::FORBIDDEN));
+        assert_eq!(check_media_path(&outside.join("secret.txt"), &roots), Err(StatusCode::FORBIDDEN));
+        assert_eq!(check_media_path(&root.join("missing.jpg"), &roots), Err(StatusCode::NOT_FOUND));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outside.join("secret.txt"), root.join("link.jpg")).unwrap();
+            assert_eq!(check_media_path(&root.join("link.jpg"), &roots), Err(StatusCode::FORBIDDEN));
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn text_file_edits_show_up_on_the_next_read() {
+        let dir = temp_dir("text-file");
+        let file = dir.join("ticker.txt");
+        let roots = [dir.clone()];
+        std::fs::write(&file, "First loop").unwrap();
+        assert_eq!(read_text_file(&file, &roots).unwrap(), "First loop");
+        std::fs::write(&file, "Second loop").unwrap();
+        assert_eq!(read_text_file(&file, &roots).unwrap(), "Second loop");
+
+        std::fs::write(&file, vec![b'a'; MAX_TEXT_FILE_BYTES as usize + 1]).unwrap();
+        assert!(read_text_file(&file, &roots).unwrap_err().contains("exceeds"));
+        let outside = temp_dir("text-file-outside").join("secret.txt");
+        std::fs::write(&outside, "secret").unwrap();
+        assert!(read_text_file(&outside, &roots).unwrap_err().contains("outside"));
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(outside.parent().unwrap());
+    }
+
+    #[test]
+    fn relative_path_resolves_against_the_base() {
+        let base = temp_dir("resolve-base");
+        std::fs::create_dir_all(base.join("photos")).unwrap();
+        let base_str = base.to_str().unwrap();
+        let resolved = resolve_path("photos", Some(base_str));
+        assert_eq!(Path::new(&resolved), base.join("photos"));
+        assert_eq!(resolve_path("photos", None), "photos");
+
+        let roots = [base.clone()];
+        let report = describe_path("photos", Some(base_str), &resolved, &roots);
+        assert_eq!(report["resolved"], resolved);
+        assert_eq!(report["base"], base_str);
+        assert_eq!((report["exists"].as_bool(), report["isDir"].as_bool()), (Some(true), Some(true)));
+
+        // Outside the roots nothing is revealed, even for a folder that exists
+        let outside = temp_dir("resolve-outside");
+        let outside_str = outside.to_str().unwrap();
+        assert_eq!(resolve_path(outside_str, Some(base_str)), outside_str);
+        let report = describe_path(outside_str, Some(base_str), outside_str, &roots);
+        assert_eq!((report["allowed"].as_bool(), report["exists"].as_bool()), (Some(false), Some(false)));
+        let _ = std::fs::remove_dir_all(&base);
+        let _ = std::fs::remove_dir_all(&outside);
+    }
+}
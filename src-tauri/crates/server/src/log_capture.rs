@@ -0,0 +1,106 @@
+//! In-memory capture of the process's stdout/stderr, so a headless display machine
+//! can be diagnosed over HTTP (`GET /api/logs`).
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+
+/// Maximum number of log lines kept in memory
+pub const LOG_BUFFER_CAPACITY: usize = 2000;
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+    pub timestamp: u64,
+    pub stream: &'static str,
+    pub line: String,
+}
+
+fn buffer() -> &'static Mutex<VecDeque<LogLine>> {
+    static BUFFER: OnceLock<Mutex<VecDeque<LogLine>>> = OnceLock::new();
+    BUFFER.get_or_init(|| Mutex::new(VecDeque::new()))
+}
+
+/// Append a line to the log buffer, dropping the oldest lines past capacity
+pub fn push_log_line(stream: &'static str, line: &str) {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    if let Ok(mut buf) = buffer().lock() {
+        buf.push_back(LogLine {
+            timestamp,
+            stream,
+            line: line.to_string(),
+        });
+        while buf.len() > LOG_BUFFER_CAPACITY {
+            buf.pop_front();
+        }
+    }
+}
+
+/// The most recent `limit` log lines, oldest first
+pub fn recent_log_lines(limit: usize) -> Vec<LogLine> {
+    buffer().lock()
+        .map(|buf| buf.iter().skip(buf.len().saturating_sub(limit)).cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Tee stdout and stderr into the log buffer. Output still reaches the original
+/// streams. Call once, early at startup; a no-op on non-Unix platforms.
+pub fn capture_process_output() {
+    #[cfg(unix)]
+    for (fd, stream) in [(libc::STDOUT_FILENO, "stdout"), (libc::STDERR_FILENO, "stderr")] {
+        if let Err(e) = unix::tee_fd(fd, stream) {
+            eprintln!("[logs] Failed to capture {}: {}", stream, e);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::File;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::fd::FromRawFd;
+
+    /// Redirect `fd` into a pipe whose reader thread forwards each line to the
+    /// original destination and into the log buffer.
+    pub(super) fn tee_fd(fd: libc::c_int, stream: &'static str) -> io::Result<()> {
+        let mut pipe_fds = [0 as libc::c_int; 2];
+        // SAFETY: plain fd syscalls; every returned fd is checked before use and the
+        // resulting Files take sole ownership of the fds they wrap.
+        let (reader, mut original) = unsafe {
+            let original = libc::dup(fd);
+            if original < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::pipe(pipe_fds.as_mut_ptr()) != 0 {
+                let err = io::Error::last_os_error();
+                libc::close(original);
+                return Err(err);
+            }
+            if libc::dup2(pipe_fds[1], fd) < 0 {
+                let err = io::Error::last_os_error();
+                libc::close(original);
+                libc::close(pipe_fds[0]);
+                libc::close(pipe_fds[1]);
+                return Err(err);
+            }
+            libc::close(pipe_fds[1]);
+            (File::from_raw_fd(pipe_fds[0]), File::from_raw_fd(original))
+        };
+
+        std::thread::Builder::new()
+            .name(format!("log-capture-{}", stream))
+            .spawn(move || {
+                let mut reader = BufReader::new(reader);
+                let mut line = Vec::new();
+                while reader.read_until(b'\n', &mut line).map(|n| n > 0).unwrap_or(false) {
+                    let _ = original.write_all(&line);
+                    super::push_log_line(stream, String::from_utf8_lossy(&line).trim_end());
+                    line.clear();
+                }
+            })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+//! Media metadata for the slideshow (`probe-media`): image dimensions via the `image`
+//! crate, and duration/size for MP4/MOV videos from the container header.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Upper bound on the `moov` box we are willing to load into memory
+const MAX_MOOV_BYTES: u64 = 32 * 1024 * 1024;
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// HEIC/HEIF (the Apple Photos default) is listed as media, but the `image` crate has no
+/// HEIF decoder. Anything that decodes pixels checks this first, so callers get a clear
+/// error instead of a generic decode failure. Returns that error for HEIF files.
+pub fn heif_unsupported(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    matches!(ext.as_str(), "heic" | "heif").then(|| format!(
+        "Cannot decode '{}': HEIC/HEIF images are not supported; serve the file untranscoded or convert it to JPEG",
+        path.display()
+    ))
+}
+
+pub fn probe_media(path: &str) -> Result<MediaInfo, String> {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    match mime.type_().as_str() {
+        "image" => {
+            let (width, height) = image::image_dimensions(path)
+                .map_err(|e| format!("Failed to read image '{}': {}", path, e))?;
+            Ok(MediaInfo { kind: "image", width: Some(width), height: Some(height), duration_ms: None })
+        }
+        "video" => {
+            let mut info = MediaInfo { kind: "video", ..Default::default() };
+            let is_iso_bmff = matches!(mime.subtype().as_str(), "mp4" | "quicktime")
+                || path.to_lowercase().ends_with(".m4v");
+            if is_iso_bmff {
+                // Best effort: unparseable containers still report the kind
+                if let Ok(Some(moov)) = read_moov(path) {
+                    parse_moov(&moov, &mut info);
+                }
+            }
+            Ok(info)
+        }
+        _ => Ok(MediaInfo { kind: "unknown", ..Default::default() }),
+    }
+}
+
+/// Read a box header at the current position: (box size including header, type, header length)
+fn read_box_header(file: &mut File, file_len: u64) -> std::io::Result<Option<(u64, [u8; 4], u64)>> {
+    let start = file.stream_position()?;
+    if start.saturating_add(8) > file_len {
+        return Ok(None);
+    }
+    let mut header = [0u8; 8];
+    file.read_exact(&mut header)?;
+    let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+    let kind = [header[4], header[5], header[6], header[7]];
+    Ok(Some(match size {
+        0 => (file_len - start, kind, 8),
+        1 => {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large)?;
+            (u64::from_be_bytes(large), kind, 16)
+        }
+        _ => (size, kind, 8),
+    }))
+}
+
+/// Find the top-level `moov` box (which may be at either end of the file) and load its body
+fn read_moov(path: &str) -> std::io::Result<Option<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut pos: u64 = 0;
+    while let Some((size, kind, header_len)) = read_box_header(&mut file, file_len)? {
+        if size < header_len {
+            return Ok(None);
+        }
+        if &kind == b"moov" {
+            let body_len = size - header_len;
+            if body_len > MAX_MOOV_BYTES {
+                return Ok(None);
+            }
+            let mut body = vec![0u8; body_len as usize];
+            file.read_exact(&mut body)?;
+            return Ok(Some(body));
+        }
+        // A crafted size can point past any real file; stop rather than overflow
+        let Some(next) = pos.checked_add(size) else {
+            return Ok(None);
+        };
+        pos = next;
+        file.seek(SeekFrom::Start(pos))?;
+    }
+    Ok(None)
+}
+
+/// Iterate child boxes of a box body as (type, body)
+fn child_boxes(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut pos = 0usize;
+    std::iter::from_fn(move || {
+        let header = data.get(pos..pos + 8)?;
+        let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let (size, header_len) = match size {
+            0 => (data.len() - pos, 8),
+            1 => {
+                let large = data.get(pos + 8..pos + 16)?;
+                (u64::from_be_bytes(large.try_into().ok()?) as usize, 16)
+            }
+            _ => (size, 8),
+        };
+        if size < header_len {
+            return None;
+        }
+        let body = data.get(pos + header_len..pos.checked_add(size)?)?;
+        let kind = &header[4..8];
+        pos += size;
+        Some((kind, body))
+    })
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn parse_moov(moov: &[u8], info: &mut MediaInfo) {
+    for (kind, body) in child_boxes(moov) {
+        match kind {
+            b"mvhd" => {
+                // version 1 uses 64-bit times and duration
+                let (timescale, duration) = if body.first() == Some(&1) {
+                    (be_u32(body, 20), be_u64(body, 24))
+                } else {
+                    (be_u32(body, 12), be_u32(body, 16).map(u64::from))
+                };
+                if let (Some(timescale), Some(duration)) = (timescale, duration) {
+                    if timescale > 0 {
+                        info.duration_ms = Some(duration.saturating_mul(1000) / timescale as u64);
+                    }
+                }
+            }
+            b"trak" if info.width.is_none() => {
+                if let Some((_, tkhd)) = child_boxes(body).find(|(k, _)| *k == b"tkhd") {
+                    // width/height are 16.16 fixed point at the end of the box
+                    let offset = if tkhd.first() == Some(&1) { 88 } else { 76 };
+                    let width = be_u32(tkhd, offset).map(|w| w >> 16);
+                    let height = be_u32(tkhd, offset + 4).map(|h| h >> 16);
+                    if let (Some(w), Some(h)) = (width, height) {
+                        if w > 0 && h > 0 {
+                            info.width = Some(w);
+                            info.height = Some(h);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("vibecast-probe-test-{}-{}", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// ISO BMFF box: 32-bit size, type, body
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    #[test]
+    fn probes_image_dimensions() {
+        let path = std::env::temp_dir().join(format!("vibecast-probe-test-{}-small.png", std::process::id()));
+        image::RgbImage::new(40, 30).save(&path).unwrap();
+        let info = probe_media(path.to_str().unwrap());
+        let _ = std::fs::remove_file(&path);
+        let info = info.unwrap();
+        assert_eq!((info.kind, info.width, info.height), ("image", Some(40), Some(30)));
+    }
+
+    #[test]
+    fn probes_mp4_duration_and_size() {
+        let mut mvhd = vec![0u8; 12];
+        mvhd.extend_from_slice(&1000u32.to_be_bytes());
+        mvhd.extend_from_slice(&5000u32.to_be_bytes());
+        let mut tkhd = vec![0u8; 76];
+        tkhd.extend_from_slice(&(1920u32 << 16).to_be_bytes());
+        tkhd.extend_from_slice(&(1080u32 << 16).to_be_bytes());
+        let trak = mp4_box(b"trak", &mp4_box(b"tkhd", &tkhd));
+        let moov = mp4_box(b"moov", &[mp4_box(b"mvhd", &mvhd), trak].concat());
+        let file = [mp4_box(b"ftyp", b"isom\0\0\0\0"), mp4_box(b"mdat", &[0; 32]), moov].concat();
+
+        let path = temp_file("clip.mp4", &file);
+        let info = probe_media(path.to_str().unwrap());
+        let _ = std::fs::remove_file(&path);
+        let info = info.unwrap();
+        assert_eq!(info.kind, "video");
+        assert_eq!(info.duration_ms, Some(5000));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+    }
+
+    #[test]
+    fn huge_box_size_stops_the_scan() {
+        // A 64-bit box size that overflows the running offset
+        let mut huge = 1u32.to_be_bytes().to_vec();
+        huge.extend_from_slice(b"free");
+        huge.extend_from_slice(&u64::MAX.to_be_bytes());
+        let file = [mp4_box(b"ftyp", b"isom\0\0\0\0"), huge].concat();
+
+        let path = temp_file("huge.mp4", &file);
+        let info = probe_media(path.to_str().unwrap());
+        let _ = std::fs::remove_file(&path);
+        let info = info.unwrap();
+        assert_eq!((info.kind, info.duration_ms), ("video", None));
+    }
+}
@@ -0,0 +1,97 @@
+//! Streaming ZIP archives of a media folder
+
+use axum::body::Bytes;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Chunk size handed to the response body
+const ZIP_CHUNK_BYTES: usize = 64 * 1024;
+
+/// `Write` adapter that forwards chunks to an async response body.
+/// Must be used from a blocking thread; fails with `BrokenPipe` once the client is gone.
+pub struct ChannelWriter {
+    tx: mpsc::Sender<Result<Bytes, io::Error>>,
+}
+
+impl ChannelWriter {
+    pub fn new(tx: mpsc::Sender<Result<Bytes, io::Error>>) -> Self {
+        Self { tx }
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx.blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write `files` into a ZIP stream under their paths relative to `root` (their file name if
+/// outside it). Entries are stored uncompressed since photos and videos are already compressed.
+pub fn write_media_zip<W: Write>(root: &Path, files: &[PathBuf], out: W) -> io::Result<()> {
+    let mut zip = ZipWriter::new_stream(io::BufWriter::with_capacity(ZIP_CHUNK_BYTES, out));
+    for path in files {
+        let name = match path.strip_prefix(root) {
+            Ok(relative) => relative.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            Err(_) => match path.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => continue,
+            },
+        };
+        let mut file = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("[Server] Skipping {} in ZIP: {}", path.display(), e);
+                continue;
+            }
+        };
+        let large = file.metadata().map(|m| m.len() > u32::MAX as u64).unwrap_or(false);
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(large);
+        zip.start_file(name, options).map_err(io::Error::other)?;
+        io::copy(&mut file, &mut zip)?;
+    }
+    zip.finish().map_err(io::Error::other)?.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn zips_a_small_folder_with_relative_names() {
+        let dir = std::env::temp_dir().join(format!("vibecast-zip-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("day2")).unwrap();
+        std::fs::write(dir.join("a.jpg"), b"first").unwrap();
+        std::fs::write(dir.join("day2").join("b.png"), b"second").unwrap();
+        let files = [dir.join("a.jpg"), dir.join("day2").join("b.png"), dir.join("missing.jpg")];
+
+        let mut out = Vec::new();
+        write_media_zip(&dir, &files, &mut out).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut archive = zip::ZipArchive::new(io::Cursor::new(out)).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut contents = String::new();
+        archive.by_name("day2/b.png").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "second");
+        contents.clear();
+        archive.by_name("a.jpg").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "first");
+    }
+}
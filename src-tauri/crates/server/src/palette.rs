@@ -0,0 +1,107 @@
+//! Dominant-color extraction for slideshow images (`extract-palette`).
+
+use image::imageops::FilterType;
+
+/// Longest side images are downsampled to before clustering
+const SAMPLE_SIZE: u32 = 64;
+const KMEANS_ITERATIONS: usize = 10;
+
+/// Extract up to `count` dominant colors from an image, most common first, as `#rrggbb`
+pub fn extract_palette(path: &str, count: usize) -> Result<Vec<String>, String> {
+    let img = image::open(path).map_err(|e| format!("Failed to load image '{}': {}", path, e))?;
+    let pixels: Vec<[f32; 3]> = img
+        .resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle)
+        .to_rgb8()
+        .pixels()
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+    if pixels.is_empty() || count == 0 {
+        return Ok(Vec::new());
+    }
+
+    // k-means with centers seeded from evenly spaced pixels (deterministic)
+    let k = count.min(pixels.len());
+    let mut centers: Vec<[f32; 3]> = (0..k).map(|i| pixels[i * pixels.len() / k]).collect();
+    let mut assignment = vec![0usize; pixels.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        for (pixel, slot) in pixels.iter().zip(assignment.iter_mut()) {
+            *slot = nearest(&centers, pixel);
+        }
+        let mut sums = vec![[0f32; 3]; k];
+        let mut counts = vec![0usize; k];
+        for (pixel, &cluster) in pixels.iter().zip(assignment.iter()) {
+            for c in 0..3 {
+                sums[cluster][c] += pixel[c];
+            }
+            counts[cluster] += 1;
+        }
+        for cluster in 0..k {
+            if counts[cluster] > 0 {
+                centers[cluster] = sums[cluster].map(|sum| sum / counts[cluster] as f32);
+            }
+        }
+    }
+
+    let mut counts = vec![0usize; k];
+    for &cluster in &assignment {
+        counts[cluster] += 1;
+    }
+    let mut clusters: Vec<(usize, String)> = centers
+        .iter()
+        .zip(counts)
+        .filter(|(_, n)| *n > 0)
+        .map(|(center, n)| {
+            let [r, g, b] = center.map(|v| v.round().clamp(0.0, 255.0) as u8);
+            (n, format!("#{:02x}{:02x}{:02x}", r, g, b))
+        })
+        .collect();
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.0));
+
+    let mut palette: Vec<String> = Vec::new();
+    for (_, hex) in clusters {
+        if !palette.contains(&hex) {
+            palette.push(hex);
+        }
+    }
+    Ok(palette)
+}
+
+fn nearest(centers: &[[f32; 3]], pixel: &[f32; 3]) -> usize {
+    let distance = |c: &[f32; 3]| (0..3).map(|i| (c[i] - pixel[i]).powi(2)).sum::<f32>();
+    centers
+        .iter()
+        .enumerate()
+        .min_by(|a, b| distance(a.1).total_cmp(&distance(b.1)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_image(name: &str, img: image::RgbImage) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("vibecast-palette-test-{}-{}", std::process::id(), name));
+        img.save_with_format(&path, image::ImageFormat::Png).unwrap();
+        path
+    }
+
+    #[test]
+    fn solid_image_has_a_single_color() {
+        let path = temp_image("solid.png", image::RgbImage::from_pixel(32, 32, image::Rgb([0x33, 0x66, 0xcc])));
+        let palette = extract_palette(path.to_str().unwrap(), 5);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(palette.unwrap(), ["#3366cc"]);
+    }
+
+    #[test]
+    fn most_common_color_comes_first() {
+        let img = image::RgbImage::from_fn(64, 64, |x, _| {
+            if x < 16 { image::Rgb([0, 0, 255]) } else { image::Rgb([255, 0, 0]) }
+        });
+        let path = temp_image("split.png", img);
+        let palette = extract_palette(path.to_str().unwrap(), 2);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(palette.unwrap(), ["#ff0000", "#0000ff"]);
+    }
+}
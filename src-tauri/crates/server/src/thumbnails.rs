@@ -0,0 +1,80 @@
+//! Disk cache of JPEG thumbnails for `/api/images/thumbnail`. Entries are keyed by source
+//! path, modification time and size, so edited files get fresh thumbnails.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Longest edge of a thumbnail when no size is requested
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+const MIN_THUMBNAIL_SIZE: u32 = 16;
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+/// Suffix for in-progress writes, so concurrent generators never see a partial file
+static TMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+pub fn clamp_size(size: Option<u32>) -> u32 {
+    size.unwrap_or(DEFAULT_THUMBNAIL_SIZE)
+        .clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE)
+}
+
+/// Where the thumbnail of `source` at `size` is cached
+pub fn cache_path(cache_dir: &Path, source: &Path, size: u32) -> std::io::Result<PathBuf> {
+    let mtime = fs::metadata(source)?.modified()?;
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    mtime.hash(&mut hasher);
+    size.hash(&mut hasher);
+    Ok(cache_dir.join(format!("{:016x}.jpg", hasher.finish())))
+}
+
+/// Path of the cached thumbnail, generating it first if it isn't cached yet
+pub fn ensure_thumbnail(cache_dir: &Path, source: &Path, size: u32) -> Result<PathBuf, String> {
+    let path = cache_path(cache_dir, source, size)
+        .map_err(|e| format!("Cannot read {}: {}", source.display(), e))?;
+    if path.exists() {
+        return Ok(path);
+    }
+    if let Some(e) = crate::media_probe::heif_unsupported(source) {
+        return Err(e);
+    }
+    fs::create_dir_all(cache_dir)
+        .map_err(|e| format!("Cannot create thumbnail cache: {}", e))?;
+    let img = image::open(source)
+        .map_err(|e| format!("Cannot decode {}: {}", source.display(), e))?;
+    let thumb = image::DynamicImage::ImageRgb8(img.thumbnail(size, size).to_rgb8());
+    let tmp = path.with_extension(format!("{}.tmp", TMP_SEQ.fetch_add(1, Ordering::Relaxed)));
+    thumb.save_with_format(&tmp, image::ImageFormat::Jpeg)
+        .map_err(|e| format!("Cannot write thumbnail: {}", e))?;
+    fs::rename(&tmp, &path)
+        .map_err(|e| format!("Cannot write thumbnail: {}", e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnail_is_generated_once_into_the_cache() {
+        let dir = std::env::temp_dir().join(format!("vibecast-thumb-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("wide.png");
+        image::RgbImage::from_pixel(400, 200, image::Rgb([200, 10, 10])).save(&source).unwrap();
+        let cache_dir = dir.join("cache");
+
+        let thumb = ensure_thumbnail(&cache_dir, &source, 100).unwrap();
+        assert!(thumb.starts_with(&cache_dir));
+        let img = image::open(&thumb).unwrap();
+        assert_eq!((img.width(), img.height()), (100, 50));
+        assert_eq!(image::ImageFormat::from_path(&thumb).unwrap(), image::ImageFormat::Jpeg);
+
+        assert_eq!(ensure_thumbnail(&cache_dir, &source, 100).unwrap(), thumb);
+        assert_ne!(ensure_thumbnail(&cache_dir, &source, 50).unwrap(), thumb);
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
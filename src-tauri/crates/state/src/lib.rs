@@ -1,46 +1,257 @@
-use std::sync::Mutex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::fs;
-use std::path::Path;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
 use tokio::sync::broadcast;
 use vibe_cast_models::{
     MessageConfig, VisualizationPreset, TextStylePreset, 
-    CommonSettings, FolderPlaybackQueue, BroadcastState, E2EReport, RemoteCommand
+    CommonSettings, Countdown, FolderPlaybackQueue, Theme, BroadcastState, E2EReport, RemoteCommand,
+    AutoDimSettings, AccessLogEntry, EventLogEntry, PersistedLogEntry, RecordedCommand, RecurringSchedule, ScheduledMessage, VersionInfo, WindowGeometry, IDLE_SPEED_MAX, IDLE_SPEED_MIN, TRIGGER_TARGET_ALL, MessageTree, flatten_message_tree_value
 };
 
-fn flatten_message_tree_value(tree: &serde_json::Value) -> Vec<MessageConfig> {
-    fn walk(node: &serde_json::Value, out: &mut Vec<MessageConfig>) {
-        match node {
-            serde_json::Value::Array(arr) => {
-                for n in arr {
-                    walk(n, out);
-                }
+/// How much `dim` drops per auto-dim tick while fading out
+const AUTO_DIM_STEP: f64 = 0.05;
+
+/// Maximum number of entries kept in the in-memory event log
+pub const EVENT_LOG_CAPACITY: usize = 1000;
+
+/// Maximum number of distinct recently-triggered message ids kept
+pub const RECENT_MESSAGES_CAPACITY: usize = 50;
+
+/// Maximum number of recent broadcast commands kept for SSE replay
+pub const COMMAND_REPLAY_CAPACITY: usize = 50;
+
+/// Maximum number of entries kept in the command access log
+pub const ACCESS_LOG_CAPACITY: usize = 1000;
+
+/// Size at which the persisted log file is rotated to `<path>.1`
+pub const LOG_FILE_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How long a destructive command's confirmation token stays valid
+pub const CONFIRM_TOKEN_TTL: Duration = Duration::from_secs(15);
+
+/// Quiet period after the last state change before an autosave is written
+const CONFIG_AUTOSAVE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Magic bytes at the start of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Config schema version written by `save_config_to_file`
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Check what an imported config can't do without: a supported `version`, the active
+/// visualization and the messages (flat `messages` or `messageTree`). Errors are
+/// `(code, message)` pairs for an API error response.
+pub fn check_required_config(config: &serde_json::Value) -> Result<(), (&'static str, String)> {
+    let Some(obj) = config.as_object() else {
+        return Err(("invalid-config", "Config root must be an object".to_string()));
+    };
+    match obj.get("version") {
+        None => return Err(("invalid-version", "Config is missing `version`".to_string())),
+        Some(v) => match v.as_u64() {
+            Some(version) if (1..=CONFIG_VERSION as u64).contains(&version) => {}
+            Some(version) => {
+                return Err((
+                    "unsupported-version",
+                    format!("Config version {} is not supported (expected 1..={})", version, CONFIG_VERSION),
+                ));
             }
-            serde_json::Value::Object(obj) => {
-                if let Some(t) = obj.get("type").and_then(|v| v.as_str()) {
-                    match t {
-                        "message" => {
-                            if let Some(msg_val) = obj.get("message") {
-                                if let Ok(msg) = serde_json::from_value::<MessageConfig>(msg_val.clone()) {
-                                    out.push(msg);
-                                }
-                            }
-                        }
-                        "folder" => {
-                            if let Some(children) = obj.get("children") {
-                                walk(children, out);
-                            }
-                        }
-                        _ => {}
+            None => return Err(("invalid-version", format!("Config version must be a positive integer, got {}", v))),
+        },
+    }
+    match obj.get("activeVisualization") {
+        None => return Err(("invalid-config", "Config is missing `activeVisualization`".to_string())),
+        Some(v) if !v.is_string() => {
+            return Err(("invalid-config", format!("`activeVisualization` must be a string, got {}", v)));
+        }
+        Some(_) => {}
+    }
+    if !obj.contains_key("messages") && !obj.contains_key("messageTree") {
+        return Err(("invalid-config", "Config needs `messages` or `messageTree`".to_string()));
+    }
+    if let Some(messages) = obj.get("messages") {
+        serde_json::from_value::<Vec<MessageConfig>>(messages.clone())
+            .map_err(|e| ("invalid-config", format!("messages: {}", e)))?;
+    }
+    if let Some(tree) = obj.get("messageTree") {
+        if !tree.is_array() {
+            return Err(("invalid-config", "messageTree must be an array".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// List every config section that would be skipped by a lenient load
+pub fn config_section_errors(config: &serde_json::Value) -> Vec<String> {
+    fn check<T: serde::de::DeserializeOwned>(obj: &serde_json::Map<String, serde_json::Value>, key: &str, errors: &mut Vec<String>) {
+        if let Some(v) = obj.get(key) {
+            if let Err(e) = serde_json::from_value::<T>(v.clone()) {
+                errors.push(format!("{}: {}", key, e));
+            }
+        }
+    }
+
+    let Some(obj) = config.as_object() else {
+        return vec!["config root must be an object".to_string()];
+    };
+
+    let mut errors = Vec::new();
+    check::<String>(obj, "activeVisualization", &mut errors);
+    check::<Vec<String>>(obj, "enabledVisualizations", &mut errors);
+    check::<CommonSettings>(obj, "commonSettings", &mut errors);
+    check::<serde_json::Map<String, serde_json::Value>>(obj, "visualizationSettings", &mut errors);
+    check::<Vec<MessageConfig>>(obj, "messages", &mut errors);
+    check::<Vec<serde_json::Value>>(obj, "messageTree", &mut errors);
+    check::<String>(obj, "defaultTextStyle", &mut errors);
+    check::<serde_json::Map<String, serde_json::Value>>(obj, "textStyleSettings", &mut errors);
+    check::<Vec<VisualizationPreset>>(obj, "visualizationPresets", &mut errors);
+    check::<String>(obj, "activeVisualizationPreset", &mut errors);
+    check::<Vec<TextStylePreset>>(obj, "textStylePresets", &mut errors);
+    check::<serde_json::Map<String, serde_json::Value>>(obj, "messageStats", &mut errors);
+
+    // Message nodes in the tree are skipped individually when they don't parse
+    if let Some(tree) = obj.get("messageTree") {
+        fn count_message_nodes(node: &serde_json::Value) -> usize {
+            match node {
+                serde_json::Value::Array(arr) => arr.iter().map(count_message_nodes).sum(),
+                serde_json::Value::Object(obj) => match obj.get("type").and_then(|v| v.as_str()) {
+                    Some("message") => 1,
+                    Some("folder") => obj.get("children").map(count_message_nodes).unwrap_or(0),
+                    _ => 0,
+                },
+                _ => 0,
+            }
+        }
+        let total = count_message_nodes(tree);
+        let parsed = flatten_message_tree_value(tree).len();
+        if parsed < total {
+            errors.push(format!("messageTree: {} of {} messages failed to parse", total - parsed, total));
+        }
+    }
+
+    errors
+}
+
+/// Structured difference between two states: top-level fields that differ (camelCase, as
+/// serialized) plus added/removed/changed ids for messages and both preset lists
+pub fn diff_states(a: &BroadcastState, b: &BroadcastState) -> serde_json::Value {
+    /// Ids in `b` but not `a`, in `a` but not `b`, and in both with different content
+    fn diff_by_id(a: Option<&serde_json::Value>, b: Option<&serde_json::Value>) -> serde_json::Value {
+        fn by_id(list: Option<&serde_json::Value>) -> Vec<(&str, &serde_json::Value)> {
+            list.and_then(|l| l.as_array())
+                .map(|items| items.iter().filter_map(|item| Some((item.get("id")?.as_str()?, item))).collect())
+                .unwrap_or_default()
+        }
+        fn find<'a>(list: &[(&str, &'a serde_json::Value)], id: &str) -> Option<&'a serde_json::Value> {
+            list.iter().find(|(i, _)| *i == id).map(|(_, v)| *v)
+        }
+        let (a, b) = (by_id(a), by_id(b));
+        let added: Vec<&str> = b.iter().filter(|(id, _)| find(&a, id).is_none()).map(|(id, _)| *id).collect();
+        let removed: Vec<&str> = a.iter().filter(|(id, _)| find(&b, id).is_none()).map(|(id, _)| *id).collect();
+        let changed: Vec<&str> = a.iter()
+            .filter(|(id, item)| find(&b, id).is_some_and(|other| other != *item))
+            .map(|(id, _)| *id)
+            .collect();
+        serde_json::json!({ "added": added, "removed": removed, "changed": changed })
+    }
+
+    let a = serde_json::to_value(a).unwrap_or_default();
+    let b = serde_json::to_value(b).unwrap_or_default();
+    let empty = serde_json::Map::new();
+    let (a_fields, b_fields) = (a.as_object().unwrap_or(&empty), b.as_object().unwrap_or(&empty));
+    let mut changed_fields: Vec<&str> = a_fields.keys()
+        .chain(b_fields.keys().filter(|k| !a_fields.contains_key(*k)))
+        .filter(|k| a_fields.get(*k) != b_fields.get(*k))
+        .map(|k| k.as_str())
+        .collect();
+    changed_fields.sort();
+
+    serde_json::json!({
+        "changedFields": changed_fields,
+        "messages": diff_by_id(a.get("messages"), b.get("messages")),
+        "visualizationPresets": diff_by_id(a.get("visualizationPresets"), b.get("visualizationPresets")),
+        "textStylePresets": diff_by_id(a.get("textStylePresets"), b.get("textStylePresets")),
+    })
+}
+
+/// Commands that control macro recording/playback are never captured into a macro
+const MACRO_CONTROL_COMMANDS: &[&str] = &["start-recording", "stop-recording", "play-macro", "list-macros"];
+
+/// Changes to `intensity`/`dim` larger than this are ramped when smoothing is on
+pub const COMMON_SETTINGS_SMOOTH_THRESHOLD: f64 = 0.1;
+
+/// How long a smoothed `common_settings` change takes
+pub const COMMON_SETTINGS_RAMP: Duration = Duration::from_millis(300);
+
+/// An in-progress ramp of `intensity`/`dim` towards new common settings
+pub struct CommonSettingsRamp {
+    pub from_intensity: f64,
+    pub from_dim: f64,
+    pub target: CommonSettings,
+    pub started: Instant,
+}
+
+/// Append-only JSONL file mirroring the event and access logs
+pub struct LogFile {
+    path: PathBuf,
+    file: fs::File,
+    size: u64,
+}
+
+impl LogFile {
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    /// The rotated previous file
+    fn rotated_path(&self) -> PathBuf {
+        let mut p = self.path.clone().into_os_string();
+        p.push(".1");
+        PathBuf::from(p)
+    }
+
+    /// Write one entry as a line, rotating first if the file would grow past `LOG_FILE_MAX_BYTES`
+    pub fn append(&mut self, entry: &PersistedLogEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        if self.size > 0 && self.size + line.len() as u64 > LOG_FILE_MAX_BYTES {
+            fs::rename(&self.path, self.rotated_path())?;
+            *self = Self::open(self.path.clone())?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// The last `n` parseable entries, oldest first, continuing into the rotated file if needed
+    pub fn tail(&self, n: usize) -> Vec<PersistedLogEntry> {
+        let mut entries: VecDeque<PersistedLogEntry> = VecDeque::new();
+        for path in [self.rotated_path(), self.path.clone()] {
+            let Ok(file) = fs::File::open(&path) else { continue };
+            for line in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+                if let Ok(entry) = serde_json::from_str(&line) {
+                    entries.push_back(entry);
+                    if entries.len() > n {
+                        entries.pop_front();
                     }
                 }
             }
-            _ => {}
         }
+        entries.into()
     }
+}
 
-    let mut out = vec![];
-    walk(tree, &mut out);
-    out
+/// A macro being recorded
+pub struct MacroRecording {
+    pub name: String,
+    pub started: Instant,
+    pub commands: Vec<RecordedCommand>,
 }
 
 /// Shared application state for syncing between windows and the remote
@@ -59,15 +270,76 @@ pub struct AppStateSync {
     pub message_stats: Mutex<serde_json::Value>,
     pub folder_playback_queue: Mutex<Option<FolderPlaybackQueue>>,
     pub config_base_path: Mutex<Option<String>>,
+    /// Config file given at launch and whether it was loaded strictly, for `reload_config`
+    pub launch_config: Mutex<Option<(String, bool)>>,
+    /// Canonical folders the local app has listed media from; `/api/images/serve` may read
+    /// files under these besides the config base path and the bundled resources
+    pub media_roots: Mutex<HashSet<PathBuf>>,
     pub server_port: Mutex<u16>,
+    /// Address to advertise in the remote URL instead of the auto-detected one
+    pub advertised_ip: Mutex<Option<std::net::IpAddr>>,
+    /// Build info of the running app, set at startup
+    pub version_info: Mutex<Option<VersionInfo>>,
     /// Last triggered message - persists until cleared
     pub triggered_message: Mutex<Option<MessageConfig>>,
+    /// When the triggered message was set (Unix ms)
+    pub triggered_at: Mutex<Option<u64>>,
+    /// Output window the triggered message targets
+    pub triggered_target: Mutex<String>,
+    /// Name of the current event/session
+    pub event_name: Mutex<Option<String>>,
+    /// Countdown shown by visualizations, if set
+    pub countdown: Mutex<Option<Countdown>>,
+    /// Visualization that was active before a message's `visualization_override` took over
+    pub visualization_before_override: Mutex<Option<String>>,
+    /// Presets from before the first `apply-theme`, restored by `reset-theme`
+    pub theme_backup: Mutex<Option<(Vec<VisualizationPreset>, Vec<TextStylePreset>)>>,
+    /// Named snapshots of the full state, for comparing show setups
+    pub snapshots: Mutex<HashMap<String, BroadcastState>>,
+    /// Distinct recently-triggered message ids, most recent first
+    pub recent_message_ids: Mutex<VecDeque<String>>,
+    /// Id of the message currently playing, as far as the backend knows
+    pub active_message_id: Mutex<Option<String>>,
+    /// Messages triggered with `queue: true`, played in order as the active message completes
+    pub message_queue: Mutex<VecDeque<MessageConfig>>,
+    /// Pending scheduled messages, ordered by due time
+    pub scheduled_messages: Mutex<Vec<ScheduledMessage>>,
+    /// Recurring schedules by id
+    pub recurring_schedules: Mutex<HashMap<String, RecurringSchedule>>,
+    /// Counter for generated schedule ids
+    pub schedule_seq: AtomicU64,
+    /// Outstanding confirmation tokens for destructive commands: token -> (command, issued at)
+    pub pending_confirmations: Mutex<HashMap<String, (String, Instant)>>,
+    /// Viz window geometry from before it went fullscreen, restored when leaving fullscreen
+    pub viz_windowed_geometry: Mutex<Option<WindowGeometry>>,
     /// Last E2E report received from frontend
     pub last_e2e_report: Mutex<Option<E2EReport>>,
+    /// Time of the last remote command, used for idle auto-dim
+    pub last_activity: Mutex<Instant>,
+    pub auto_dim: Mutex<AutoDimSettings>,
+    /// `dim` value from before auto-dim kicked in, restored on the next activity
+    pub auto_dim_restore: Mutex<Option<f64>>,
+    /// Ramp large `intensity`/`dim` changes instead of jumping
+    pub smooth_common_settings: AtomicBool,
+    pub common_settings_ramp: Mutex<Option<CommonSettingsRamp>>,
+    /// Macro currently being recorded, if any
+    pub macro_recording: Mutex<Option<MacroRecording>>,
+    /// Recorded macros by name
+    pub macros: Mutex<HashMap<String, Vec<RecordedCommand>>>,
+    /// Recent events, oldest first, bounded by `EVENT_LOG_CAPACITY`
+    pub event_log: Mutex<VecDeque<EventLogEntry>>,
+    /// Id of the last event log entry
+    pub event_log_seq: AtomicU64,
+    /// Which clients sent which commands, oldest first, bounded by `ACCESS_LOG_CAPACITY`
+    pub access_log: Mutex<VecDeque<AccessLogEntry>>,
+    /// File the event and access logs are appended to, if enabled
+    pub log_file: Mutex<Option<LogFile>>,
     /// Broadcast channel for SSE - sends full state on every change
     pub state_tx: broadcast::Sender<BroadcastState>,
     /// Broadcast channel for commands - sends transient commands (like report-status)
     pub command_tx: broadcast::Sender<RemoteCommand>,
+    /// Recently broadcast commands, oldest first, replayed to late-joining SSE clients
+    pub recent_commands: Mutex<VecDeque<RemoteCommand>>,
 }
 
 impl Default for AppStateSync {
@@ -94,6 +366,9 @@ impl AppStateSync {
                 speed: None,
                 split_enabled: None,
                 split_separator: None,
+                background_color: None,
+                overlay_opacity: None,
+                visualization_override: None,
             },
             MessageConfig {
                 id: "msg-2".to_string(),
@@ -106,6 +381,9 @@ impl AppStateSync {
                 speed: Some(1.0),
                 split_enabled: Some(true),
                 split_separator: Some(",".to_string()),
+                background_color: None,
+                overlay_opacity: None,
+                visualization_override: None,
             },
             MessageConfig {
                 id: "msg-3".to_string(),
@@ -118,6 +396,9 @@ impl AppStateSync {
                 speed: None,
                 split_enabled: None,
                 split_separator: None,
+                background_color: None,
+                overlay_opacity: None,
+                visualization_override: None,
             },
         ];
 
@@ -297,11 +578,42 @@ impl AppStateSync {
             message_stats: Mutex::new(serde_json::json!({})),
             folder_playback_queue: Mutex::new(None),
             config_base_path: Mutex::new(None),
+            launch_config: Mutex::new(None),
+            media_roots: Mutex::new(HashSet::new()),
             server_port: Mutex::new(0), // 0 indicates not yet bound
+            advertised_ip: Mutex::new(None),
+            version_info: Mutex::new(None),
             triggered_message: Mutex::new(None),
+            triggered_at: Mutex::new(None),
+            triggered_target: Mutex::new(TRIGGER_TARGET_ALL.to_string()),
+            event_name: Mutex::new(None),
+            countdown: Mutex::new(None),
+            visualization_before_override: Mutex::new(None),
+            theme_backup: Mutex::new(None),
+            snapshots: Mutex::new(HashMap::new()),
+            recent_message_ids: Mutex::new(VecDeque::new()),
+            active_message_id: Mutex::new(None),
+            message_queue: Mutex::new(VecDeque::new()),
+            scheduled_messages: Mutex::new(Vec::new()),
+            recurring_schedules: Mutex::new(HashMap::new()),
+            schedule_seq: AtomicU64::new(0),
+            pending_confirmations: Mutex::new(HashMap::new()),
+            viz_windowed_geometry: Mutex::new(None),
             last_e2e_report: Mutex::new(None),
+            last_activity: Mutex::new(Instant::now()),
+            auto_dim: Mutex::new(AutoDimSettings::default()),
+            auto_dim_restore: Mutex::new(None),
+            smooth_common_settings: AtomicBool::new(false),
+            common_settings_ramp: Mutex::new(None),
+            macro_recording: Mutex::new(None),
+            macros: Mutex::new(HashMap::new()),
+            event_log: Mutex::new(VecDeque::new()),
+            event_log_seq: AtomicU64::new(0),
+            access_log: Mutex::new(VecDeque::new()),
+            log_file: Mutex::new(None),
             state_tx,
             command_tx,
+            recent_commands: Mutex::new(VecDeque::new()),
         }
     }
 
@@ -319,9 +631,12 @@ impl AppStateSync {
         let visualization_settings = self.visualization_settings.lock()
             .map(|m| m.clone())
             .unwrap_or_else(|_| serde_json::json!({}));
-        let visualization_presets = self.visualization_presets.lock()
+        // Canonical preset ordering is applied here, at snapshot time, rather than on every
+        // mutation. Presets without an `order` go last; the sort is stable.
+        let mut visualization_presets = self.visualization_presets.lock()
             .map(|m| m.clone())
             .unwrap_or_default();
+        visualization_presets.sort_by_key(|p| (p.order.is_none(), p.order));
         let active_visualization_preset = self.active_visualization_preset.lock()
             .map(|m| m.clone())
             .unwrap_or(None);
@@ -349,6 +664,28 @@ impl AppStateSync {
         let triggered_message = self.triggered_message.lock()
             .map(|m| m.clone())
             .unwrap_or(None);
+        let triggered_at = self.triggered_at.lock()
+            .map(|m| *m)
+            .unwrap_or(None);
+        let triggered_target = self.triggered_target.lock()
+            .map(|m| m.clone())
+            .unwrap_or_else(|_| TRIGGER_TARGET_ALL.to_string());
+        let event_name = self.event_name.lock()
+            .map(|m| m.clone())
+            .unwrap_or(None);
+        let queued_messages = self.message_queue.lock()
+            .map(|q| q.iter().cloned().collect())
+            .unwrap_or_default();
+        let scheduled_messages = self.scheduled_messages.lock()
+            .map(|m| m.clone())
+            .unwrap_or_default();
+        let countdown = self.countdown.lock()
+            .map(|c| c.clone())
+            .unwrap_or(None);
+        let mut recurring_schedules: Vec<RecurringSchedule> = self.recurring_schedules.lock()
+            .map(|m| m.values().cloned().collect())
+            .unwrap_or_default();
+        recurring_schedules.sort_by_key(|r| r.next_at);
         
         // Legacy mode field
         let mode = active_visualization.clone();
@@ -367,44 +704,810 @@ impl AppStateSync {
             text_style_presets,
             message_stats,
             triggered_message,
+            triggered_at,
+            triggered_target,
             folder_playback_queue,
+            queued_messages,
+            scheduled_messages,
+            recurring_schedules,
+            event_name,
+            countdown,
             mode,
         }
     }
 
+    /// Store the current state under `name`, replacing any snapshot with that name
+    pub fn save_snapshot(&self, name: &str) {
+        let state = self.get_state();
+        if let Ok(mut snapshots) = self.snapshots.lock() {
+            snapshots.insert(name.to_string(), state);
+        }
+    }
+
+    /// `diff_states` of two named snapshots; errors name a snapshot that doesn't exist
+    pub fn diff_snapshots(&self, a: &str, b: &str) -> Result<serde_json::Value, String> {
+        let snapshots = self.snapshots.lock().map_err(|_| "Snapshots unavailable".to_string())?;
+        let find = |name: &str| snapshots.get(name).ok_or_else(|| format!("No snapshot named '{}'", name));
+        Ok(diff_states(find(a)?, find(b)?))
+    }
+
+    /// Messages whose text contains `query`, in tree order, as `(id, folder path)`. Messages
+    /// missing from the tree come last with no folder. `file_texts` holds the current
+    /// `textFile` contents by message id, which are searched too.
+    pub fn search_messages(
+        &self,
+        query: &str,
+        case_sensitive: bool,
+        file_texts: &HashMap<String, String>,
+    ) -> Vec<(String, Option<String>)> {
+        let normalize = |s: &str| if case_sensitive { s.to_string() } else { s.to_lowercase() };
+        let query = normalize(query);
+
+        let tree = self.message_tree.lock()
+            .map(|t| MessageTree::from_value(&t))
+            .unwrap_or_default();
+        let mut candidates: Vec<(MessageConfig, Option<String>)> = tree.messages_with_folder_path()
+            .into_iter()
+            .map(|(msg, path)| (msg.clone(), Some(path)))
+            .collect();
+        if let Ok(messages) = self.messages.lock() {
+            for msg in messages.iter() {
+                if !candidates.iter().any(|(m, _)| m.id == msg.id) {
+                    candidates.push((msg.clone(), None));
+                }
+            }
+        }
+
+        candidates.into_iter()
+            .filter(|(msg, _)| {
+                normalize(&msg.text).contains(&query)
+                    || file_texts.get(&msg.id).is_some_and(|text| normalize(text).contains(&query))
+            })
+            .map(|(msg, folder_path)| (msg.id, folder_path))
+            .collect()
+    }
+
     /// Broadcast current state to all SSE subscribers
     pub fn broadcast(&self, triggered_message: Option<MessageConfig>) {
         // Store triggered_message in state so it persists across broadcasts
         if let Ok(mut tm) = self.triggered_message.lock() {
             *tm = triggered_message.clone();
         }
+        if let Ok(mut at) = self.triggered_at.lock() {
+            *at = triggered_message.as_ref().map(|_| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0)
+            });
+        }
         let state = self.get_state();
         // Ignore send errors (no subscribers)
         let _ = self.state_tx.send(state);
     }
     
+    /// Replace the text of the displayed message in place. The id and trigger time are kept so
+    /// the visualizer patches the text instead of restarting the message.
+    /// Returns the updated message, or `None` if nothing is displayed.
+    pub fn update_triggered_text(&self, text: &str) -> Option<MessageConfig> {
+        let mut tm = self.triggered_message.lock().ok()?;
+        let msg = tm.as_mut()?;
+        msg.text = text.to_string();
+        Some(msg.clone())
+    }
+
+    /// Set the output window targeted by the next triggered message
+    pub fn set_triggered_target(&self, target: &str) {
+        if let Ok(mut t) = self.triggered_target.lock() {
+            *t = target.to_string();
+        }
+    }
+
+    /// Add a scheduled message, generating an id if it has none. Returns the id.
+    pub fn schedule_message(&self, mut scheduled: ScheduledMessage) -> String {
+        if scheduled.id.is_empty() {
+            let n = self.schedule_seq.fetch_add(1, Ordering::Relaxed) + 1;
+            scheduled.id = format!("sched-{}", n);
+        }
+        let id = scheduled.id.clone();
+        if let Ok(mut list) = self.scheduled_messages.lock() {
+            list.retain(|s| s.id != id);
+            list.push(scheduled);
+            list.sort_by_key(|s| s.at);
+        }
+        id
+    }
+
+    /// Remove a scheduled message. Returns false if no such id was pending.
+    pub fn cancel_scheduled(&self, id: &str) -> bool {
+        self.scheduled_messages.lock()
+            .map(|mut list| {
+                let before = list.len();
+                list.retain(|s| s.id != id);
+                list.len() != before
+            })
+            .unwrap_or(false)
+    }
+
+    /// Take all scheduled messages due at `now` (Unix ms)
+    pub fn take_due_scheduled(&self, now: u64) -> Vec<ScheduledMessage> {
+        self.scheduled_messages.lock()
+            .map(|mut list| {
+                let due = list.iter().take_while(|s| s.at <= now).count();
+                list.drain(..due).collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Add (or replace, by id) a recurring schedule. Generates an id if none is given.
+    pub fn schedule_recurring(&self, mut recurring: RecurringSchedule) -> String {
+        if recurring.id.is_empty() {
+            let n = self.schedule_seq.fetch_add(1, Ordering::Relaxed) + 1;
+            recurring.id = format!("recur-{}", n);
+        }
+        let id = recurring.id.clone();
+        if let Ok(mut map) = self.recurring_schedules.lock() {
+            map.insert(id.clone(), recurring);
+        }
+        id
+    }
+
+    /// Stop a recurring schedule. Returns false if no such id exists.
+    pub fn cancel_recurring(&self, id: &str) -> bool {
+        self.recurring_schedules.lock()
+            .map(|mut map| map.remove(id).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Drop every pending scheduled message and recurring schedule
+    pub fn clear_schedules(&self) {
+        if let Ok(mut list) = self.scheduled_messages.lock() {
+            list.clear();
+        }
+        if let Ok(mut map) = self.recurring_schedules.lock() {
+            map.clear();
+        }
+    }
+
+    /// Message ids of recurring schedules due at `now` (Unix ms). Due schedules move on to
+    /// their next interval; ones whose `until` has passed are removed.
+    pub fn take_due_recurring(&self, now: u64) -> Vec<String> {
+        let Ok(mut map) = self.recurring_schedules.lock() else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        for recurring in map.values_mut() {
+            if recurring.next_at <= now && !recurring.is_finished() {
+                due.push(recurring.message_id.clone());
+                recurring.advance(now);
+            }
+        }
+        map.retain(|_, r| !r.is_finished());
+        due
+    }
+
+    /// Issue a token that confirms one run of `command` within `CONFIRM_TOKEN_TTL`
+    pub fn issue_confirmation(&self, command: &str) -> String {
+        let n = self.schedule_seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let token = format!("{:x}{:08x}", n, nanos);
+        if let Ok(mut pending) = self.pending_confirmations.lock() {
+            pending.retain(|_, (_, issued)| issued.elapsed() < CONFIRM_TOKEN_TTL);
+            pending.insert(token.clone(), (command.to_string(), Instant::now()));
+        }
+        token
+    }
+
+    /// Use up a confirmation token. Returns false if it is unknown, expired or was issued
+    /// for a different command.
+    pub fn consume_confirmation(&self, command: &str, token: &str) -> bool {
+        let Ok(mut pending) = self.pending_confirmations.lock() else {
+            return false;
+        };
+        match pending.remove(token) {
+            Some((cmd, issued)) => cmd == command && issued.elapsed() < CONFIRM_TOKEN_TTL,
+            None => false,
+        }
+    }
+
+    /// Move a triggered message to the front of the recent list
+    pub fn record_recent_trigger(&self, message_id: &str) {
+        if let Ok(mut recent) = self.recent_message_ids.lock() {
+            recent.retain(|id| id != message_id);
+            recent.push_front(message_id.to_string());
+            recent.truncate(RECENT_MESSAGES_CAPACITY);
+        }
+    }
+
+    /// The most recently triggered messages that still exist, most recent first
+    pub fn recent_messages(&self, limit: usize) -> Vec<MessageConfig> {
+        let (Ok(recent), Ok(messages)) = (self.recent_message_ids.lock(), self.messages.lock()) else {
+            return Vec::new();
+        };
+        recent.iter()
+            .filter_map(|id| messages.iter().find(|m| &m.id == id).cloned())
+            .take(limit)
+            .collect()
+    }
+
+    /// Allow media under `dir` to be served over HTTP
+    pub fn allow_media_root(&self, dir: &Path) {
+        match fs::canonicalize(dir) {
+            Ok(dir) => {
+                if let Ok(mut roots) = self.media_roots.lock() {
+                    roots.insert(dir);
+                }
+            }
+            Err(e) => eprintln!("[Rust] Cannot allow media folder {}: {}", dir.display(), e),
+        }
+    }
+
+    /// Record which message is currently playing (`None` once nothing is)
+    pub fn set_active_message(&self, message_id: Option<&str>) {
+        if let Ok(mut active) = self.active_message_id.lock() {
+            *active = message_id.map(|id| id.to_string());
+        }
+    }
+
+    /// Show a throwaway message on every display without storing it or recording stats
+    pub fn show_flash(&self, msg: MessageConfig) {
+        self.set_triggered_target(TRIGGER_TARGET_ALL);
+        self.set_active_message(Some(&msg.id));
+        self.broadcast(Some(msg));
+    }
+
+    /// End the flash `flash_id` if it is still the active message. Returns false if something
+    /// replaced it in the meantime, which is then left alone.
+    pub fn clear_flash(&self, flash_id: &str) -> bool {
+        let cleared = self.active_message_id.lock()
+            .map(|mut active| {
+                let flashing = active.as_deref() == Some(flash_id);
+                if flashing {
+                    *active = None;
+                }
+                flashing
+            })
+            .unwrap_or(false);
+        if cleared {
+            self.broadcast(None);
+        }
+        cleared
+    }
+
+    /// Switch to the visualization override of the message now playing (`None` once nothing
+    /// is), or restore the visualization from before the override if it has none.
+    /// The original visualization is kept across consecutive overrides.
+    /// Returns the new active visualization if it changed.
+    pub fn apply_visualization_override(&self, message: Option<&MessageConfig>) -> Option<String> {
+        let (Ok(mut active), Ok(mut before)) = (
+            self.active_visualization.lock(),
+            self.visualization_before_override.lock(),
+        ) else {
+            return None;
+        };
+        let next = match message.and_then(|m| m.visualization_override.as_ref()) {
+            Some(viz) => {
+                if before.is_none() {
+                    *before = Some(active.clone());
+                }
+                viz.clone()
+            }
+            None => before.take()?,
+        };
+        if *active == next {
+            return None;
+        }
+        *active = next.clone();
+        Some(next)
+    }
+
+    /// Recolor all visualization and text style presets with `theme`, backing up the
+    /// presets first unless a theme is already applied. Returns the number of settings changed.
+    pub fn apply_theme(&self, theme: &Theme) -> usize {
+        let (Ok(mut viz), Ok(mut text), Ok(mut backup)) = (
+            self.visualization_presets.lock(),
+            self.text_style_presets.lock(),
+            self.theme_backup.lock(),
+        ) else {
+            return 0;
+        };
+        if backup.is_none() {
+            *backup = Some((viz.clone(), text.clone()));
+        }
+        viz.iter_mut().map(|p| theme.apply_to_settings(&mut p.settings)).sum::<usize>()
+            + text.iter_mut().map(|p| theme.apply_to_settings(&mut p.settings)).sum::<usize>()
+    }
+
+    /// Restore the presets from before the theme was applied. Returns false if no theme is applied.
+    pub fn reset_theme(&self) -> bool {
+        let (Ok(mut viz), Ok(mut text), Ok(mut backup)) = (
+            self.visualization_presets.lock(),
+            self.text_style_presets.lock(),
+            self.theme_backup.lock(),
+        ) else {
+            return false;
+        };
+        let Some((viz_backup, text_backup)) = backup.take() else {
+            return false;
+        };
+        *viz = viz_backup;
+        *text = text_backup;
+        true
+    }
+
+    /// Queue a message behind the active one. Returns false (and queues nothing) when
+    /// nothing is playing, in which case the caller should play it right away.
+    pub fn enqueue_message(&self, msg: MessageConfig) -> bool {
+        let busy = self.active_message_id.lock().map(|a| a.is_some()).unwrap_or(false)
+            || self.folder_playback_queue.lock().map(|q| q.is_some()).unwrap_or(false);
+        if !busy {
+            return false;
+        }
+        if let Ok(mut queue) = self.message_queue.lock() {
+            queue.push_back(msg);
+        }
+        true
+    }
+
+    /// Called when `completed_id` stops playing. Once no folder queue is running, pops the
+    /// next queued message and marks it active.
+    pub fn advance_message_queue(&self, completed_id: &str) -> Option<MessageConfig> {
+        let mut active = self.active_message_id.lock().ok()?;
+        if active.as_deref() == Some(completed_id) {
+            *active = None;
+        }
+        if active.is_some() || self.folder_playback_queue.lock().map(|q| q.is_some()).unwrap_or(true) {
+            return None;
+        }
+        let next = self.message_queue.lock().ok()?.pop_front()?;
+        *active = Some(next.id.clone());
+        Some(next)
+    }
+
+    /// The message the folder queue will play next, with the queue speed applied
+    pub fn peek_next_queue_message(&self) -> Option<MessageConfig> {
+        let queue = self.folder_playback_queue.lock().ok()?;
+        let q = queue.as_ref()?;
+        let next_id = q.next_message_id()?;
+        let mut msg = self.messages.lock().ok()?
+            .iter()
+            .find(|m| m.id == next_id)
+            .cloned()?;
+        q.apply_speed(&mut msg);
+        Some(msg)
+    }
+
+    /// Broadcast current state without changing the triggered message
+    pub fn broadcast_state(&self) {
+        let state = self.get_state();
+        let _ = self.state_tx.send(state);
+    }
+    
     /// Broadcast a transient command to all SSE subscribers
     pub fn broadcast_command(&self, command: RemoteCommand) {
+        if let Ok(mut recent) = self.recent_commands.lock() {
+            recent.push_back(command.clone());
+            while recent.len() > COMMAND_REPLAY_CAPACITY {
+                recent.pop_front();
+            }
+        }
         let _ = self.command_tx.send(command);
     }
+
+    /// The last `n` broadcast commands, oldest first
+    pub fn recent_commands(&self, n: usize) -> Vec<RemoteCommand> {
+        self.recent_commands.lock()
+            .map(|recent| recent.iter().skip(recent.len().saturating_sub(n)).cloned().collect())
+            .unwrap_or_default()
+    }
     
     /// Clear the triggered message (called when message completes)
     pub fn clear_triggered_message(&self) {
         if let Ok(mut tm) = self.triggered_message.lock() {
             *tm = None;
         }
+        if let Ok(mut at) = self.triggered_at.lock() {
+            *at = None;
+        }
         // Broadcast the cleared state
         let state = self.get_state();
         let _ = self.state_tx.send(state);
     }
 
-    /// Load configuration from a JSON file
-    pub fn load_config_from_file(&self, config_path: &str) -> Result<(), String> {
+    /// Append an entry to the event log, dropping the oldest entries past capacity
+    pub fn log_event(&self, event: &str, detail: Option<serde_json::Value>) -> u64 {
+        let id = self.event_log_seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let entry = EventLogEntry {
+            id,
+            timestamp,
+            event: event.to_string(),
+            detail,
+        };
+        self.persist_log(|| PersistedLogEntry::Event(entry.clone()));
+        if let Ok(mut log) = self.event_log.lock() {
+            log.push_back(entry);
+            while log.len() > EVENT_LOG_CAPACITY {
+                log.pop_front();
+            }
+        }
+        id
+    }
+
+    /// Append an access log entry, dropping the oldest entries past capacity
+    pub fn record_access(&self, entry: AccessLogEntry) {
+        self.persist_log(|| PersistedLogEntry::Access(entry.clone()));
+        if let Ok(mut log) = self.access_log.lock() {
+            log.push_back(entry);
+            while log.len() > ACCESS_LOG_CAPACITY {
+                log.pop_front();
+            }
+        }
+    }
+
+    /// Append to the log file, if one is enabled
+    fn persist_log(&self, entry: impl FnOnce() -> PersistedLogEntry) {
+        if let Ok(mut guard) = self.log_file.lock() {
+            if let Some(file) = guard.as_mut() {
+                if let Err(e) = file.append(&entry()) {
+                    eprintln!("Failed to write log file: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Start appending the event and access logs to `path` as JSONL. The last `reload` entries
+    /// already in the file are loaded back into the in-memory logs first, and event ids
+    /// continue from the highest one seen.
+    pub fn enable_log_file(&self, path: &str, reload: usize) -> Result<(), String> {
+        let file = LogFile::open(path).map_err(|e| format!("Failed to open log file {}: {}", path, e))?;
+        if reload > 0 {
+            let (Ok(mut events), Ok(mut access)) = (self.event_log.lock(), self.access_log.lock()) else {
+                return Err("Log state unavailable".to_string());
+            };
+            for entry in file.tail(reload) {
+                match entry {
+                    PersistedLogEntry::Event(e) => {
+                        self.event_log_seq.fetch_max(e.id, Ordering::Relaxed);
+                        events.push_back(e);
+                    }
+                    PersistedLogEntry::Access(a) => access.push_back(a),
+                }
+            }
+            while events.len() > EVENT_LOG_CAPACITY {
+                events.pop_front();
+            }
+            while access.len() > ACCESS_LOG_CAPACITY {
+                access.pop_front();
+            }
+        }
+        if let Ok(mut guard) = self.log_file.lock() {
+            *guard = Some(file);
+        }
+        Ok(())
+    }
+
+    /// Event log entries with `id > after`, oldest first, at most `limit` of them
+    pub fn event_log_after(&self, after: u64, limit: usize) -> Vec<EventLogEntry> {
+        self.event_log.lock()
+            .map(|log| log.iter().filter(|e| e.id > after).take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Record remote activity, undoing any idle auto-dim.
+    /// Returns true if the dim level was restored.
+    pub fn touch_activity(&self) -> bool {
+        if let Ok(mut t) = self.last_activity.lock() {
+            *t = Instant::now();
+        }
+        let restore = self.auto_dim_restore.lock()
+            .ok()
+            .and_then(|mut r| r.take());
+        if let Some(dim) = restore {
+            if let Ok(mut m) = self.common_settings.lock() {
+                m.dim = dim;
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Dim the display fully. Any pending auto-dim restore is dropped, so the next activity
+    /// doesn't bring the old brightness back.
+    pub fn blank_display(&self) {
+        if let Ok(mut r) = self.auto_dim_restore.lock() {
+            *r = None;
+        }
+        if let Ok(mut m) = self.common_settings.lock() {
+            m.dim = 0.0;
+        }
+    }
+
+    /// Step the idle auto-dim fade. Returns true if `dim` changed and should be broadcast.
+    pub fn auto_dim_tick(&self) -> bool {
+        let settings = self.auto_dim.lock()
+            .map(|m| m.clone())
+            .unwrap_or_default();
+        let Some(after_ms) = settings.after_ms else {
+            return false;
+        };
+        let idle_ms = self.last_activity.lock()
+            .map(|t| t.elapsed().as_millis() as u64)
+            .unwrap_or(0);
+        if idle_ms < after_ms {
+            return false;
+        }
+
+        let Ok(mut common) = self.common_settings.lock() else {
+            return false;
+        };
+        if common.dim <= settings.floor {
+            return false;
+        }
+        if let Ok(mut r) = self.auto_dim_restore.lock() {
+            if r.is_none() {
+                *r = Some(common.dim);
+            }
+        }
+        common.dim = (common.dim - AUTO_DIM_STEP).max(settings.floor);
+        true
+    }
+
+    /// Apply new common settings. With smoothing on, a large `intensity`/`dim` change starts
+    /// a ramp (advanced by `common_settings_ramp_tick`) instead of applying at once; other
+    /// fields still apply immediately. Returns true if a ramp was started.
+    pub fn set_common_settings(&self, mut settings: CommonSettings) -> bool {
+        settings.idle_speed = settings.idle_speed.clamp(IDLE_SPEED_MIN, IDLE_SPEED_MAX);
+        let (Ok(mut current), Ok(mut ramp)) = (self.common_settings.lock(), self.common_settings_ramp.lock()) else {
+            return false;
+        };
+        let large_change = (settings.intensity - current.intensity).abs() > COMMON_SETTINGS_SMOOTH_THRESHOLD
+            || (settings.dim - current.dim).abs() > COMMON_SETTINGS_SMOOTH_THRESHOLD;
+        if self.smooth_common_settings.load(Ordering::Relaxed) && large_change {
+            // Retargeting mid-ramp starts from wherever the ramp had got to
+            *ramp = Some(CommonSettingsRamp {
+                from_intensity: current.intensity,
+                from_dim: current.dim,
+                target: settings.clone(),
+                started: Instant::now(),
+            });
+            current.audio_reactive = settings.audio_reactive;
+            current.idle_speed = settings.idle_speed;
+            current.output_aspect = settings.output_aspect.clone();
+            true
+        } else {
+            *ramp = None;
+            *current = settings;
+            false
+        }
+    }
+
+    /// Advance the common settings ramp, if any. Returns true if the settings changed.
+    pub fn common_settings_ramp_tick(&self) -> bool {
+        let (Ok(mut current), Ok(mut ramp)) = (self.common_settings.lock(), self.common_settings_ramp.lock()) else {
+            return false;
+        };
+        let Some(r) = ramp.as_ref() else {
+            return false;
+        };
+        let t = (r.started.elapsed().as_secs_f64() / COMMON_SETTINGS_RAMP.as_secs_f64()).min(1.0);
+        if t >= 1.0 {
+            *current = r.target.clone();
+            *ramp = None;
+            return true;
+        }
+        // Smoothstep easing
+        let eased = t * t * (3.0 - 2.0 * t);
+        current.intensity = r.from_intensity + (r.target.intensity - r.from_intensity) * eased;
+        current.dim = r.from_dim + (r.target.dim - r.from_dim) * eased;
+        true
+    }
+
+    /// Start recording commands into a macro, discarding any unfinished recording
+    pub fn start_recording(&self, name: &str) {
+        if let Ok(mut r) = self.macro_recording.lock() {
+            *r = Some(MacroRecording {
+                name: name.to_string(),
+                started: Instant::now(),
+                commands: Vec::new(),
+            });
+        }
+    }
+
+    /// Finish the current recording and store it. Returns the macro name and command count.
+    pub fn stop_recording(&self) -> Option<(String, usize)> {
+        let recording = self.macro_recording.lock().ok()?.take()?;
+        let count = recording.commands.len();
+        if let Ok(mut macros) = self.macros.lock() {
+            macros.insert(recording.name.clone(), recording.commands);
+        }
+        Some((recording.name, count))
+    }
+
+    /// Capture a command into the active recording, if any
+    pub fn record_command(&self, command: &RemoteCommand) {
+        if MACRO_CONTROL_COMMANDS.contains(&command.command.as_str()) {
+            return;
+        }
+        if let Ok(mut r) = self.macro_recording.lock() {
+            if let Some(recording) = r.as_mut() {
+                recording.commands.push(RecordedCommand {
+                    offset_ms: recording.started.elapsed().as_millis() as u64,
+                    command: command.clone(),
+                });
+            }
+        }
+    }
+
+    /// Current state in the config schema read by `load_config_from_file`
+    pub fn config_json(&self) -> serde_json::Value {
+        let state = self.get_state();
+        serde_json::json!({
+            "version": CONFIG_VERSION,
+            "activeVisualization": state.active_visualization,
+            "activeVisualizationPreset": state.active_visualization_preset,
+            "enabledVisualizations": state.enabled_visualizations,
+            "visualizationPresets": state.visualization_presets,
+            "commonSettings": state.common_settings,
+            "visualizationSettings": state.visualization_settings,
+            "messages": state.messages,
+            "messageTree": state.message_tree,
+            "defaultTextStyle": state.default_text_style,
+            "textStyleSettings": state.text_style_settings,
+            "textStylePresets": state.text_style_presets,
+            "messageStats": state.message_stats,
+        })
+    }
+
+    /// Write the current state to `config_path` in the config schema.
+    /// Writes a sibling temp file first so a crash mid-write can't truncate the config.
+    pub fn save_config_to_file(&self, config_path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.config_json())
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        let tmp_path = format!("{}.tmp", config_path);
+        fs::write(&tmp_path, content)
+            .map_err(|e| format!("Failed to write config file: {}", e))?;
+        fs::rename(&tmp_path, config_path)
+            .map_err(|e| format!("Failed to replace config file: {}", e))?;
+        Ok(())
+    }
+
+    /// Write the message stats to `stats_path`, gzip-compressed when the path ends in `.gz`
+    /// (trigger history grows over a long-running install)
+    pub fn save_stats_to_file(&self, stats_path: &str) -> Result<(), String> {
+        let stats = self.message_stats.lock()
+            .map(|m| m.clone())
+            .unwrap_or_else(|_| serde_json::json!({}));
+        let json = serde_json::to_string(&stats)
+            .map_err(|e| format!("Failed to serialize stats: {}", e))?;
+        let content = if stats_path.ends_with(".gz") {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(json.as_bytes())
+                .and_then(|_| encoder.finish())
+                .map_err(|e| format!("Failed to compress stats: {}", e))?
+        } else {
+            json.into_bytes()
+        };
+        let tmp_path = format!("{}.tmp", stats_path);
+        fs::write(&tmp_path, content)
+            .map_err(|e| format!("Failed to write stats file: {}", e))?;
+        fs::rename(&tmp_path, stats_path)
+            .map_err(|e| format!("Failed to replace stats file: {}", e))?;
+        Ok(())
+    }
+
+    /// Replace the message stats with the contents of `stats_path`. Gzipped files are
+    /// detected by content, whatever the extension.
+    pub fn load_stats_from_file(&self, stats_path: &str) -> Result<(), String> {
+        let bytes = fs::read(stats_path)
+            .map_err(|e| format!("Failed to read stats file: {}", e))?;
+        let stats: serde_json::Value = if bytes.starts_with(&GZIP_MAGIC) {
+            serde_json::from_reader(flate2::read::GzDecoder::new(bytes.as_slice()))
+        } else {
+            serde_json::from_slice(&bytes)
+        }
+        .map_err(|e| format!("Failed to parse stats file: {}", e))?;
+        if !stats.is_object() {
+            return Err("Stats file must contain a JSON object".to_string());
+        }
+        if let Ok(mut m) = self.message_stats.lock() {
+            *m = stats;
+        }
+        Ok(())
+    }
+
+    /// Save the config to `config_path` whenever state changes, once changes have been quiet
+    /// for `CONFIG_AUTOSAVE_DEBOUNCE`. Every mutation broadcasts state, so the state channel
+    /// is the change signal.
+    pub fn spawn_config_autosave(self: &Arc<Self>, config_path: String) {
+        let mut rx = self.state_tx.subscribe();
+        let sync = Arc::downgrade(self);
+        let spawned = std::thread::Builder::new()
+            .name("config-autosave".to_string())
+            .spawn(move || {
+                use broadcast::error::{RecvError, TryRecvError};
+                loop {
+                    match rx.blocking_recv() {
+                        Ok(_) | Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => return,
+                    }
+                    // Keep waiting until no further change arrives within the debounce window
+                    loop {
+                        std::thread::sleep(CONFIG_AUTOSAVE_DEBOUNCE);
+                        let mut changed = false;
+                        loop {
+                            match rx.try_recv() {
+                                Ok(_) | Err(TryRecvError::Lagged(_)) => changed = true,
+                                Err(TryRecvError::Empty) => break,
+                                Err(TryRecvError::Closed) => return,
+                            }
+                        }
+                        if !changed {
+                            break;
+                        }
+                    }
+                    let Some(sync) = sync.upgrade() else { return };
+                    match sync.save_config_to_file(&config_path) {
+                        Ok(()) => eprintln!("[Rust] Autosaved config to: {}", config_path),
+                        Err(e) => eprintln!("[Rust] Config autosave failed: {}", e),
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            eprintln!("[Rust] Failed to start config autosave: {}", e);
+        }
+    }
+
+    /// Load the launch config file again (e.g. after editing it). Returns its path.
+    pub fn reload_config(&self) -> Result<String, String> {
+        let (path, strict) = self.launch_config.lock()
+            .ok()
+            .and_then(|c| c.clone())
+            .ok_or("No config path is known (the app was started without a config file)")?;
+        self.load_config_from_file(&path, strict)?;
+        Ok(path)
+    }
+
+    /// Load configuration from a JSON file.
+    ///
+    /// Lenient mode skips sections that fail to parse. Strict mode fails on any such section,
+    /// listing all of them, and applies nothing.
+    pub fn load_config_from_file(&self, config_path: &str, strict: bool) -> Result<(), String> {
         let path = Path::new(config_path);
         if !path.exists() {
             return Err(format!("Config file does not exist: {}", config_path));
         }
         
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file: {}", e))?;
+        
+        // Hand-edited files often have trailing commas or comments; outside strict mode,
+        // retry with the lenient JSON5 parser before giving up
+        let config: serde_json::Value = match serde_json::from_str(&content) {
+            Ok(config) => config,
+            Err(e) if strict => return Err(format!("Failed to parse config JSON: {}", e)),
+            Err(e) => match json5::from_str(&content) {
+                Ok(config) => {
+                    eprintln!("[Rust] Config is not strict JSON ({}); loaded it as JSON5", e);
+                    config
+                }
+                Err(_) => return Err(format!("Failed to parse config JSON: {}", e)),
+            },
+        };
+        
+        if strict {
+            let errors = config_section_errors(&config);
+            if !errors.is_empty() {
+                return Err(format!("Invalid config sections: {}", errors.join("; ")));
+            }
+        }
+        
         // Extract and set the config base path (directory containing the config file)
         if let Some(parent) = path.parent() {
             let base_path = parent.to_string_lossy().to_string();
@@ -414,12 +1517,6 @@ impl AppStateSync {
             }
         }
         
-        let content = fs::read_to_string(path)
-            .map_err(|e| format!("Failed to read config file: {}", e))?;
-        
-        let config: serde_json::Value = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse config JSON: {}", e))?;
-        
         // Apply configuration similar to the "load-configuration" command handler
         if let Some(obj) = config.as_object() {
             if let Some(viz) = obj.get("activeVisualization").and_then(|v| v.as_str()) {
@@ -458,7 +1555,7 @@ impl AppStateSync {
                     *t = tree.clone();
                 }
                 // Ensure flattened messages match tree
-                let flat = flatten_message_tree_value(&tree);
+                let flat = flatten_message_tree_value(tree);
                 if let Ok(mut m) = self.messages.lock() {
                     *m = flat;
                 }
@@ -520,3 +1617,529 @@ impl AppStateSync {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh path in the system temp dir, unique to this test process
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("vibecast-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn saved_config_loads_back_equal() {
+        let sync = AppStateSync::new();
+        *sync.active_visualization.lock().unwrap() = "techno".to_string();
+        *sync.default_text_style.lock().unwrap() = "fade".to_string();
+        sync.common_settings.lock().unwrap().intensity = 0.5;
+        let path = temp_path("roundtrip.json");
+        let path = path.to_str().unwrap();
+        sync.save_config_to_file(path).unwrap();
+
+        let loaded = AppStateSync::new();
+        loaded.load_config_from_file(path, true).unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(loaded.config_json(), sync.config_json());
+    }
+
+    #[test]
+    fn diff_snapshots_reports_changed_visualization_and_message() {
+        let sync = AppStateSync::new();
+        sync.save_snapshot("a");
+        *sync.active_visualization.lock().unwrap() = "techno".to_string();
+        sync.messages.lock().unwrap()[0].text = "Changed".to_string();
+        sync.save_snapshot("b");
+
+        let diff = sync.diff_snapshots("a", "b").unwrap();
+        // `mode` is the legacy mirror of the active visualization
+        assert_eq!(diff["changedFields"], serde_json::json!(["activeVisualization", "messages", "mode"]));
+        assert_eq!(diff["messages"]["changed"], serde_json::json!(["msg-1"]));
+        assert_eq!(diff["messages"]["added"], serde_json::json!([]));
+        assert_eq!(diff["messages"]["removed"], serde_json::json!([]));
+        assert_eq!(diff["visualizationPresets"]["changed"], serde_json::json!([]));
+
+        assert!(sync.diff_snapshots("a", "missing").is_err());
+    }
+
+    #[test]
+    fn clear_schedules_drops_scheduled_and_recurring() {
+        let sync = AppStateSync::new();
+        sync.schedule_message(ScheduledMessage {
+            id: String::new(),
+            at: 1_000,
+            payload: serde_json::json!({ "id": "msg-1" }),
+            expires_after_ms: None,
+        });
+        sync.schedule_recurring(RecurringSchedule {
+            id: String::new(),
+            message_id: "msg-1".to_string(),
+            interval_ms: 60_000,
+            until: None,
+            next_at: 1_000,
+        });
+        sync.clear_schedules();
+        assert!(sync.scheduled_messages.lock().unwrap().is_empty());
+        assert!(sync.recurring_schedules.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn blanked_display_stays_dark_after_activity() {
+        let sync = AppStateSync::new();
+        *sync.auto_dim_restore.lock().unwrap() = Some(0.8);
+        sync.blank_display();
+        assert!(!sync.touch_activity());
+        assert!(!sync.auto_dim_tick());
+        assert_eq!(sync.common_settings.lock().unwrap().dim, 0.0);
+    }
+
+    #[test]
+    fn compressed_stats_load_back_equal() {
+        let sync = AppStateSync::new();
+        *sync.message_stats.lock().unwrap() = serde_json::json!({
+            "msg-1": {
+                "messageId": "msg-1",
+                "triggerCount": 2,
+                "lastTriggered": 2_000,
+                "history": [{ "timestamp": 1_000 }, { "timestamp": 2_000 }]
+            }
+        });
+        let path = temp_path("stats.json.gz");
+        let path = path.to_str().unwrap();
+        sync.save_stats_to_file(path).unwrap();
+        let bytes = fs::read(path).unwrap();
+        assert!(bytes.starts_with(&GZIP_MAGIC));
+
+        let loaded = AppStateSync::new();
+        loaded.load_stats_from_file(path).unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(*loaded.message_stats.lock().unwrap(), *sync.message_stats.lock().unwrap());
+    }
+
+    #[test]
+    fn search_default_messages() {
+        let sync = AppStateSync::new();
+        let folder = Some("Party Countdown".to_string());
+        let none = HashMap::new();
+        assert_eq!(
+            sync.search_messages("COUNTDOWN", false, &none),
+            vec![("msg-1".to_string(), folder.clone())]
+        );
+        assert!(sync.search_messages("COUNTDOWN", true, &none).is_empty());
+        let ids: Vec<String> = sync.search_messages("T", false, &none)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, ["msg-1", "msg-3"]);
+
+        let file_texts = HashMap::from([("msg-2".to_string(), "Happy new year".to_string())]);
+        assert_eq!(
+            sync.search_messages("new year", false, &file_texts),
+            vec![("msg-2".to_string(), folder)]
+        );
+    }
+
+    #[test]
+    fn flash_leaves_stored_messages_alone() {
+        let sync = AppStateSync::new();
+        let before = serde_json::to_value(&*sync.messages.lock().unwrap()).unwrap();
+        let mut flash = sync.messages.lock().unwrap()[0].clone();
+        flash.id = "__flash".to_string();
+        flash.text = "Doors close in 5 minutes".to_string();
+        sync.show_flash(flash);
+        assert_eq!(sync.active_message_id.lock().unwrap().as_deref(), Some("__flash"));
+
+        assert!(sync.clear_flash("__flash"));
+        assert!(sync.active_message_id.lock().unwrap().is_none());
+        assert!(!sync.clear_flash("__flash"));
+        assert_eq!(serde_json::to_value(&*sync.messages.lock().unwrap()).unwrap(), before);
+        assert!(sync.message_stats.lock().unwrap().as_object().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reset_theme_restores_presets_from_before_the_first_theme() {
+        let sync = AppStateSync::new();
+        let presets = |sync: &AppStateSync| serde_json::json!([
+            *sync.visualization_presets.lock().unwrap(),
+            *sync.text_style_presets.lock().unwrap(),
+        ]);
+        let before = presets(&sync);
+        let red = Theme { accent: Some("#ff0000".to_string()), background: Some("#110000".to_string()), text: Some("#ffeeee".to_string()) };
+        let blue = Theme { accent: Some("#0000ff".to_string()), background: None, text: None };
+        assert!(sync.apply_theme(&red) > 0);
+        sync.apply_theme(&blue);
+        assert_ne!(presets(&sync), before);
+
+        assert!(sync.reset_theme());
+        assert_eq!(presets(&sync), before);
+        assert!(!sync.reset_theme());
+    }
+
+    #[test]
+    fn import_requires_version_visualization_and_messages() {
+        let sync = AppStateSync::new();
+        let exported = sync.config_json();
+        assert!(check_required_config(&exported).is_ok());
+
+        let without = |key: &str| {
+            let mut config = exported.clone();
+            config.as_object_mut().unwrap().remove(key);
+            config
+        };
+        assert_eq!(check_required_config(&without("version")).unwrap_err().0, "invalid-version");
+        assert_eq!(check_required_config(&without("activeVisualization")).unwrap_err().0, "invalid-config");
+        let mut no_messages = without("messages");
+        no_messages.as_object_mut().unwrap().remove("messageTree");
+        assert_eq!(check_required_config(&no_messages).unwrap_err().0, "invalid-config");
+        assert!(check_required_config(&without("messages")).is_ok());
+
+        let mut future = exported.clone();
+        future["version"] = serde_json::json!(CONFIG_VERSION + 1);
+        assert_eq!(check_required_config(&future).unwrap_err().0, "unsupported-version");
+        let mut bad_messages = exported.clone();
+        bad_messages["messages"] = serde_json::json!([{ "id": 1 }]);
+        assert_eq!(check_required_config(&bad_messages).unwrap_err().0, "invalid-config");
+        assert_eq!(check_required_config(&serde_json::json!([])).unwrap_err().0, "invalid-config");
+    }
+
+    #[test]
+    fn get_state_sorts_presets_by_order_with_unordered_last() {
+        let sync = AppStateSync::new();
+        let preset = |id: &str, order: Option<u32>| VisualizationPreset {
+            id: id.to_string(),
+            name: id.to_string(),
+            visualization_id: "fireplace".to_string(),
+            settings: serde_json::json!({}),
+            enabled: None,
+            order,
+            icon: None,
+        };
+        *sync.visualization_presets.lock().unwrap() = vec![
+            preset("none-a", None),
+            preset("second", Some(2)),
+            preset("first", Some(1)),
+            preset("none-b", None),
+            preset("also-second", Some(2)),
+        ];
+        let ids: Vec<String> = sync.get_state().visualization_presets.into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, ["first", "second", "also-second", "none-a", "none-b"]);
+        // Stored order is left alone
+        assert_eq!(sync.visualization_presets.lock().unwrap()[0].id, "none-a");
+    }
+
+    #[test]
+    fn event_log_after_cursor_returns_only_newer_entries() {
+        let sync = AppStateSync::new();
+        let ids: Vec<u64> = (0..5).map(|i| sync.log_event(&format!("event-{}", i), None)).collect();
+        let after = |cursor: u64, limit: usize| -> Vec<u64> {
+            sync.event_log_after(cursor, limit).into_iter().map(|e| e.id).collect()
+        };
+        assert_eq!(after(ids[1], 10), ids[2..]);
+        assert_eq!(after(ids[1], 2), ids[2..4]);
+        assert!(after(ids[4], 10).is_empty());
+
+        for i in 0..EVENT_LOG_CAPACITY {
+            sync.log_event(&format!("filler-{}", i), None);
+        }
+        let kept = sync.event_log_after(0, usize::MAX);
+        assert_eq!(kept.len(), EVENT_LOG_CAPACITY);
+        assert_eq!(kept[0].id, ids[4] + 1);
+    }
+
+    #[test]
+    fn trailing_comma_config_loads_via_json5_fallback() {
+        let path = temp_path("trailing-comma.json");
+        fs::write(&path, "{\n  \"version\": 1,\n  \"activeVisualization\": \"techno\",\n  \"defaultTextStyle\": \"fade\",\n}\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let strict = AppStateSync::new();
+        assert!(strict.load_config_from_file(path, true).is_err());
+
+        let lenient = AppStateSync::new();
+        let loaded = lenient.load_config_from_file(path, false);
+        let _ = fs::remove_file(path);
+        loaded.unwrap();
+        assert_eq!(*lenient.active_visualization.lock().unwrap(), "techno");
+        assert_eq!(*lenient.default_text_style.lock().unwrap(), "fade");
+    }
+
+    #[test]
+    fn confirmation_tokens_are_single_use_per_command() {
+        let sync = AppStateSync::new();
+        let token = sync.issue_confirmation("stop-all");
+        assert!(!sync.consume_confirmation("reset-message-stats", &token));
+
+        let token = sync.issue_confirmation("stop-all");
+        assert!(sync.consume_confirmation("stop-all", &token));
+        assert!(!sync.consume_confirmation("stop-all", &token));
+        assert!(!sync.consume_confirmation("stop-all", "unknown"));
+
+        let token = sync.issue_confirmation("delete-folder-messages");
+        let expired = Instant::now().checked_sub(CONFIRM_TOKEN_TTL).unwrap();
+        sync.pending_confirmations.lock().unwrap().get_mut(&token).unwrap().1 = expired;
+        assert!(!sync.consume_confirmation("delete-folder-messages", &token));
+    }
+
+    #[test]
+    fn output_aspect_applies_at_once_while_intensity_ramps() {
+        let sync = AppStateSync::new();
+        sync.smooth_common_settings.store(true, Ordering::Relaxed);
+        let mut target = sync.common_settings.lock().unwrap().clone();
+        target.intensity = 0.2;
+        target.output_aspect = Some("21:9".to_string());
+        assert!(sync.set_common_settings(target.clone()));
+
+        let current = sync.common_settings.lock().unwrap().clone();
+        assert_eq!(current.output_aspect.as_deref(), Some("21:9"));
+        assert_ne!(current.intensity, target.intensity);
+    }
+
+    #[test]
+    fn large_intensity_change_ramps_through_intermediate_values() {
+        let sync = AppStateSync::new();
+        sync.smooth_common_settings.store(true, Ordering::Relaxed);
+        let mut target = sync.common_settings.lock().unwrap().clone();
+        let from = target.intensity;
+        target.intensity = 0.0;
+        assert!(sync.set_common_settings(target));
+
+        // Halfway through the ramp
+        sync.common_settings_ramp.lock().unwrap().as_mut().unwrap().started =
+            Instant::now().checked_sub(COMMON_SETTINGS_RAMP / 2).unwrap();
+        assert!(sync.common_settings_ramp_tick());
+        let mid = sync.common_settings.lock().unwrap().intensity;
+        assert!(mid > 0.0 && mid < from, "intensity {} should be between 0 and {}", mid, from);
+
+        sync.common_settings_ramp.lock().unwrap().as_mut().unwrap().started =
+            Instant::now().checked_sub(COMMON_SETTINGS_RAMP).unwrap();
+        assert!(sync.common_settings_ramp_tick());
+        assert_eq!(sync.common_settings.lock().unwrap().intensity, 0.0);
+        assert!(sync.common_settings_ramp.lock().unwrap().is_none());
+        assert!(!sync.common_settings_ramp_tick());
+    }
+
+    #[test]
+    fn queued_triggers_play_in_order_after_the_active_one() {
+        let sync = AppStateSync::new();
+        let messages = sync.messages.lock().unwrap().clone();
+        assert!(!sync.enqueue_message(messages[0].clone()));
+
+        sync.set_active_message(Some("msg-1"));
+        assert!(sync.enqueue_message(messages[1].clone()));
+        assert!(sync.enqueue_message(messages[2].clone()));
+
+        // Completion of something else doesn't cut the active message short
+        assert!(sync.advance_message_queue("msg-3").is_none());
+        assert_eq!(sync.advance_message_queue("msg-1").map(|m| m.id).as_deref(), Some("msg-2"));
+        assert_eq!(sync.active_message_id.lock().unwrap().as_deref(), Some("msg-2"));
+        assert_eq!(sync.advance_message_queue("msg-2").map(|m| m.id).as_deref(), Some("msg-3"));
+        assert!(sync.advance_message_queue("msg-3").is_none());
+        assert!(sync.active_message_id.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn idle_display_dims_to_the_floor_and_restores_on_activity() {
+        let sync = AppStateSync::new();
+        sync.common_settings.lock().unwrap().dim = 1.0;
+        *sync.auto_dim.lock().unwrap() = AutoDimSettings { after_ms: Some(60_000), floor: 0.2 };
+        assert!(!sync.auto_dim_tick());
+
+        *sync.last_activity.lock().unwrap() = Instant::now().checked_sub(Duration::from_secs(120)).unwrap();
+        assert!(sync.auto_dim_tick());
+        let dimmed = sync.common_settings.lock().unwrap().dim;
+        assert!(dimmed < 1.0);
+        while sync.auto_dim_tick() {}
+        assert_eq!(sync.common_settings.lock().unwrap().dim, 0.2);
+
+        assert!(sync.touch_activity());
+        assert_eq!(sync.common_settings.lock().unwrap().dim, 1.0);
+    }
+
+    #[test]
+    fn log_file_writes_jsonl_and_rotates_when_full() {
+        let path = temp_path("events.jsonl");
+        let mut rotated = path.clone().into_os_string();
+        rotated.push(".1");
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&rotated);
+        let entry = |id: u64| PersistedLogEntry::Event(EventLogEntry {
+            id,
+            timestamp: 1_000 + id,
+            event: format!("event-{}", id),
+            detail: None,
+        });
+
+        let mut log = LogFile::open(&path).unwrap();
+        log.append(&entry(1)).unwrap();
+        let line = fs::read_to_string(&path).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(parsed["kind"], "event");
+        assert_eq!(parsed["event"], "event-1");
+
+        // Pretend the file is full, so the next entry starts a new one
+        log.size = LOG_FILE_MAX_BYTES;
+        log.append(&entry(2)).unwrap();
+        let rotated_text = fs::read_to_string(&rotated).unwrap();
+        let current_text = fs::read_to_string(&path).unwrap();
+        assert_eq!(rotated_text, line);
+        assert!(current_text.contains("event-2") && !current_text.contains("event-1"));
+        let ids: Vec<u64> = log.tail(10).into_iter()
+            .map(|e| match e {
+                PersistedLogEntry::Event(e) => e.id,
+                PersistedLogEntry::Access(_) => 0,
+            })
+            .collect();
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&rotated);
+        assert_eq!(ids, [1, 2]);
+    }
+
+    #[test]
+    fn recurring_schedule_fires_each_interval_until_it_ends() {
+        let sync = AppStateSync::new();
+        let id = sync.schedule_recurring(RecurringSchedule {
+            id: String::new(),
+            message_id: "msg-2".to_string(),
+            interval_ms: 1_000,
+            until: Some(3_500),
+            next_at: 1_000,
+        });
+        assert!(sync.take_due_recurring(999).is_empty());
+        assert_eq!(sync.take_due_recurring(1_000), ["msg-2"]);
+        assert!(sync.take_due_recurring(1_500).is_empty());
+        assert_eq!(sync.take_due_recurring(2_000), ["msg-2"]);
+        // Firings missed while asleep are skipped, not replayed
+        assert_eq!(sync.take_due_recurring(3_200), ["msg-2"]);
+        assert!(!sync.recurring_schedules.lock().unwrap().contains_key(&id));
+        assert!(sync.take_due_recurring(10_000).is_empty());
+    }
+
+    #[test]
+    fn visualization_override_is_undone_after_the_message() {
+        let sync = AppStateSync::new();
+        *sync.active_visualization.lock().unwrap() = "fireplace".to_string();
+        let mut first = sync.messages.lock().unwrap()[0].clone();
+        first.visualization_override = Some("techno".to_string());
+        let mut second = first.clone();
+        second.visualization_override = Some("particles".to_string());
+
+        assert_eq!(sync.apply_visualization_override(Some(&first)).as_deref(), Some("techno"));
+        assert_eq!(sync.apply_visualization_override(Some(&second)).as_deref(), Some("particles"));
+        assert_eq!(sync.apply_visualization_override(None).as_deref(), Some("fireplace"));
+        assert_eq!(*sync.active_visualization.lock().unwrap(), "fireplace");
+        assert!(sync.apply_visualization_override(None).is_none());
+    }
+
+    #[test]
+    fn recent_messages_are_most_recent_first_without_duplicates() {
+        let sync = AppStateSync::new();
+        for id in ["msg-1", "msg-2", "deleted", "msg-3", "msg-1"] {
+            sync.record_recent_trigger(id);
+        }
+        let recent = |limit: usize| -> Vec<String> {
+            sync.recent_messages(limit).into_iter().map(|m| m.id).collect()
+        };
+        assert_eq!(recent(10), ["msg-1", "msg-3", "msg-2"]);
+        assert_eq!(recent(2), ["msg-1", "msg-3"]);
+    }
+
+    #[test]
+    fn autosave_writes_the_config_after_a_change() {
+        let sync = Arc::new(AppStateSync::new());
+        let path = temp_path("autosave.json");
+        let _ = fs::remove_file(&path);
+        sync.spawn_config_autosave(path.to_str().unwrap().to_string());
+
+        *sync.active_visualization.lock().unwrap() = "techno".to_string();
+        sync.broadcast_state();
+        let deadline = Instant::now() + CONFIG_AUTOSAVE_DEBOUNCE * 10;
+        let saved = loop {
+            if let Some(config) = fs::read_to_string(&path).ok()
+                .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+            {
+                break config;
+            }
+            assert!(Instant::now() < deadline, "config was not autosaved");
+            std::thread::sleep(CONFIG_AUTOSAVE_DEBOUNCE / 4);
+        };
+        let _ = fs::remove_file(&path);
+        assert_eq!(saved["activeVisualization"], "techno");
+    }
+
+    #[test]
+    fn reload_config_picks_up_edits_to_the_launch_file() {
+        let sync = AppStateSync::new();
+        assert!(sync.reload_config().is_err());
+
+        let path = temp_path("reload.json");
+        let path_str = path.to_str().unwrap().to_string();
+        sync.save_config_to_file(&path_str).unwrap();
+        *sync.launch_config.lock().unwrap() = Some((path_str.clone(), true));
+
+        let mut config: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        config["activeVisualization"] = serde_json::json!("techno");
+        fs::write(&path, config.to_string()).unwrap();
+        let reloaded = sync.reload_config();
+        let _ = fs::remove_file(&path);
+        assert_eq!(reloaded.unwrap(), path_str);
+        assert_eq!(*sync.active_visualization.lock().unwrap(), "techno");
+    }
+
+    #[test]
+    fn malformed_preset_fails_strict_load_but_not_lenient() {
+        let path = temp_path("malformed-preset.json");
+        fs::write(&path, serde_json::json!({
+            "version": 1,
+            "activeVisualization": "techno",
+            "visualizationPresets": [{ "id": "broken", "name": 42 }],
+        }).to_string()).unwrap();
+        let path = path.to_str().unwrap();
+
+        let strict = AppStateSync::new();
+        let err = strict.load_config_from_file(path, true).unwrap_err();
+        assert!(err.contains("visualizationPresets"), "{}", err);
+        assert_ne!(*strict.active_visualization.lock().unwrap(), "techno");
+
+        let lenient = AppStateSync::new();
+        let presets_before = serde_json::to_value(&*lenient.visualization_presets.lock().unwrap()).unwrap();
+        let loaded = lenient.load_config_from_file(path, false);
+        let _ = fs::remove_file(path);
+        loaded.unwrap();
+        assert_eq!(*lenient.active_visualization.lock().unwrap(), "techno");
+        assert_eq!(serde_json::to_value(&*lenient.visualization_presets.lock().unwrap()).unwrap(), presets_before);
+    }
+
+    #[test]
+    fn updated_text_is_broadcast_without_restamping_the_trigger() {
+        let sync = AppStateSync::new();
+        assert!(sync.update_triggered_text("Nothing shown").is_none());
+        let msg = sync.messages.lock().unwrap()[0].clone();
+        sync.broadcast(Some(msg));
+        let triggered_at = *sync.triggered_at.lock().unwrap();
+
+        let mut rx = sync.state_tx.subscribe();
+        let updated = sync.update_triggered_text("Doors open").unwrap();
+        assert_eq!(updated.id, "msg-1");
+        sync.broadcast_state();
+        let state = rx.try_recv().unwrap();
+        assert_eq!(state.triggered_message.unwrap().text, "Doors open");
+        assert_eq!(state.triggered_at, triggered_at);
+        // The stored message keeps its text
+        assert_eq!(sync.messages.lock().unwrap()[0].text, "Countdown initiated...");
+    }
+
+    #[test]
+    fn triggered_at_is_stamped_with_the_current_time() {
+        let now_ms = || std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+        let sync = AppStateSync::new();
+        let msg = sync.messages.lock().unwrap()[1].clone();
+        let before = now_ms();
+        sync.broadcast(Some(msg));
+        let after = now_ms();
+        let at = sync.get_state().triggered_at.unwrap();
+        assert!(before <= at && at <= after, "{} not within {}..={}", at, before, after);
+
+        sync.broadcast(None);
+        assert!(sync.get_state().triggered_at.is_none());
+    }
+}
@@ -478,6 +478,21 @@ export const VisualizerWindow: React.FC = () => {
         }
         break;
       }
+      case 'update-active-message-text': {
+        // Patch the text in place; keeping the timestamp avoids restarting the message
+        if (payload && typeof payload === 'object' && 'messageId' in payload) {
+          const { messageId, text } = payload as { messageId: string; text: string };
+          useStore.setState((state) => ({
+            activeMessages: state.activeMessages.map((am) =>
+              am.message.id === messageId ? { ...am, message: { ...am.message, text } } : am
+            ),
+            activeMessage: state.activeMessage?.id === messageId
+              ? { ...state.activeMessage, text }
+              : state.activeMessage,
+          }));
+        }
+        break;
+      }
       case 'set-common-settings':
         // eslint-disable-next-line @typescript-eslint/no-explicit-any
         setCommonSettings(payload as any, false);
@@ -18,6 +18,40 @@ export interface FolderPlaybackQueue {
   folderId: string;
   messageIds: string[];
   currentIndex: number;
+  preview?: boolean;
+  speedMultiplier?: number;
+  paused?: boolean;
+  shuffle?: boolean;
+  loopPlayback?: boolean;
+}
+
+/**
+ * A trigger-message payload scheduled to fire at a Unix time (ms)
+ */
+export interface ScheduledMessage {
+  id: string;
+  at: number;
+  payload: unknown;
+  expiresAfterMs?: number;
+}
+
+/**
+ * A message re-triggered every intervalMs until cancelled or `until` (Unix ms) passes
+ */
+export interface RecurringSchedule {
+  id: string;
+  messageId: string;
+  intervalMs: number;
+  until?: number;
+  nextAt: number;
+}
+
+/**
+ * Countdown target (Unix ms) for "T-minus" displays
+ */
+export interface Countdown {
+  target: number;
+  label?: string;
 }
 
 /**
@@ -37,8 +71,15 @@ export interface AppState {
   messages: MessageConfig[];
   messageTree?: MessageTreeNode[];
   triggeredMessage?: MessageConfig | null;
+  triggeredAt?: number;
+  triggeredTarget?: string;
+  eventName?: string;
   messageStats?: Record<string, MessageStats>;
   folderPlaybackQueue?: FolderPlaybackQueue | null;
+  queuedMessages?: MessageConfig[];
+  scheduledMessages?: ScheduledMessage[];
+  recurringSchedules?: RecurringSchedule[];
+  countdown?: Countdown;
   
   // Text style state
   defaultTextStyle: string;
@@ -94,6 +135,7 @@ function parseSSEState(data: any): AppState {
       triggeredMessage: data.triggeredMessage ?? null,
       messageStats: data.messageStats ?? (typeof data.messageStats === 'object' ? data.messageStats : {}),
       folderPlaybackQueue: data.folderPlaybackQueue ?? null,
+      countdown: data.countdown ?? undefined,
       defaultTextStyle: data.defaultTextStyle ?? 'scrolling-capitals',
       textStyleSettings: data.textStyleSettings ?? {},
       textStylePresets: data.textStylePresets ?? [],
@@ -95,6 +95,9 @@ export function getDefaultsFromSchema(schema: SettingDefinition[]): Record<strin
 export interface CommonVisualizationSettings {
   intensity: number;  // 0-1, 1 = raw audio, lower = more smoothing
   dim: number;        // 0-1, 1 = full brightness, 0 = black
+  audioReactive?: boolean;  // false = ignore audio, keep idle animation (default true)
+  outputAspect?: string;    // output display aspect ratio hint, e.g. "16:9"
+  idleSpeed?: number;       // 0-5 animation speed while audio is silent or reactivity is off (default 1)
 }
 
 export const DEFAULT_COMMON_SETTINGS: CommonVisualizationSettings = {
@@ -165,6 +168,17 @@ export interface TextStyleProps {
   onComplete?: () => void;
 }
 
+/**
+ * Text glow modulation by audio, stored under `textStyleSettings.audioCoupling`
+ * (set with the `set-text-audio-coupling` command)
+ */
+export interface TextAudioCoupling {
+  enabled: boolean;
+  band: 'low' | 'mid' | 'high';
+  /** 0 (no modulation) to 1 (full glow swing) */
+  amount: number;
+}
+
 /**
  * Definition of a text style plugin
  */
@@ -287,6 +301,15 @@ export interface MessageConfig {
    * Parts are trimmed; empty separators disable splitting.
    */
   splitSeparator?: string;
+
+  /** Optional: Force a background color (hex, e.g. "#ff0000") regardless of the active visualization */
+  backgroundColor?: string;
+
+  /** Optional: Opacity (0..1) of the backgroundColor overlay */
+  overlayOpacity?: number;
+
+  /** Optional: Visualization to switch to while this message plays (restored afterwards) */
+  visualizationOverride?: string;
   
   /** Text style plugin ID to use (legacy - kept for backward compatibility) */
   textStyle: string;
@@ -316,6 +339,8 @@ export interface MessageFolderNode {
   name: string;
   /** Whether folder is collapsed in the UI */
   collapsed?: boolean;
+  /** Default text style for messages in this folder that don't set their own */
+  defaultTextStyle?: string;
   /** Child nodes */
   children: MessageTreeNode[];
 }